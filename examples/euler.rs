@@ -0,0 +1,92 @@
+//! Estimates Euler's number: on average e uniform(0, 1) delays are needed before their sum exceeds 1.
+//! Run with `cargo run --release --example euler`.
+
+use tapn::{
+    ArcType, Batch, Comparison, Distribution, InputArc, Marking, OutputArc, Place, RegularOutputArc,
+    Tapn, TapnError, TokenAgeObserver, TokenCoutObserver, TokenSelection, Transition, TransportArc,
+    TransportOutputArc,
+};
+
+fn main() -> Result<(), TapnError> {
+    let num_simulations = 100000; // Number of simulations to run
+    let base_seed = 42; // Every run gets its own seed derived from this one
+
+    // Create places with unique IDs, arcs refer to them by their index
+    let accumulated_time = 0;
+    let finished = 1;
+    let places = vec![
+        Place::new(0, "accumulated_time".to_string(), vec![]),
+        Place::new(1, "finished".to_string(), vec![]),
+    ];
+
+    let marking = Marking::new(vec![
+        vec![0.0],  // Start with one token at age 0.0
+        vec![],  // Start empty
+    ]);
+
+    // Create delay transition (non-urgent)
+    let delay = Transition::new(
+        vec![ArcType::Transport(TransportArc {
+            input: accumulated_time,
+            weight: 1,
+            timing: [0.0, f64::INFINITY],
+            selection: TokenSelection::OldestEligible,
+            pair: 0,
+        })],
+        vec![OutputArc::TransportArc(TransportOutputArc {
+            output: accumulated_time,
+            weight: 1,
+            pair: 0,
+        })],
+        Distribution::Uniform(0.0, 1.0),
+        false,
+        0,  // Transition ID 0
+    );
+
+    // Create timeout transition (urgent)
+    let timeout = Transition::new(
+        vec![ArcType::Input(InputArc {
+            input: accumulated_time,
+            weight: 1,
+            timing: [1.0, f64::INFINITY],  // Only fire when token age ≥1.0
+            selection: TokenSelection::OldestEligible,
+        })],
+        vec![OutputArc::Regular(RegularOutputArc {
+            output: finished,
+            weight: 1,
+        })],
+        Distribution::Constant(0.0),
+        true,  // Fires the moment the token reaches age 1.0
+        1,  // Transition ID 1
+    );
+
+    // Create and configure observers
+    let count_observer = Box::new(TokenCoutObserver::new()
+        .monitor_place(1, 1, Comparison::Equal));  // Stop when place 1 (finished) has exactly 1 token
+        
+    let age_observer = Box::new(TokenAgeObserver::new(10.0));  // Emergency stop if any token age > 10.0
+
+    // Create TAPN model
+    let mut euler = Tapn::new(
+        places,
+        vec![delay, timeout],
+        marking,
+    )?;
+
+    // Add observers
+    euler.add_observer(count_observer);
+    euler.add_observer(age_observer);
+
+    // Run the simulations on all cores, each until an observer stops it
+    let results = Batch::new(num_simulations, base_seed).run(&euler)?;
+
+    // Calculate averages
+    let avg_steps = results.mean_steps();
+    let avg_firings = results.mean_firings(0); // Count of delay transitions fired
+
+    // Print results
+    println!("Ran {} simulations", num_simulations);
+    println!("Average steps to completion: {:?}", avg_steps);
+    println!("Average delay transition firings: {:?}", avg_firings);
+    Ok(())
+}
\ No newline at end of file
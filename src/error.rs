@@ -0,0 +1,97 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::{
+    DistributionError, FiringError, JsonError, ParameterError, QueryError, TapaalError,
+    ValidationError,
+};
+
+/// Any error the engine can run into while loading, building or simulating a net
+#[derive(Debug)]
+pub enum TapnError {
+    Firing(FiringError),
+    /// The distribution of a transition could not be sampled
+    Sampling {
+        transition: usize,
+        error: DistributionError,
+    },
+    Invalid(ValidationError),
+    Tapaal(TapaalError),
+    Json(JsonError),
+    Query(QueryError),
+    Parameter(ParameterError),
+    /// The file extension doesn't name a model format
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for TapnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapnError::Firing(error) => write!(f, "{error}"),
+            TapnError::Sampling { transition, error } => {
+                write!(f, "transition {transition}: {error}")
+            }
+            TapnError::Invalid(error) => write!(f, "{error}"),
+            TapnError::Tapaal(error) => write!(f, "{error}"),
+            TapnError::Json(error) => write!(f, "{error}"),
+            TapnError::Query(error) => write!(f, "{error}"),
+            TapnError::Parameter(error) => write!(f, "{error}"),
+            TapnError::UnknownFormat(path) => write!(
+                f,
+                "can't tell the format of {}, expected a .json, .tapn, .xml or .pnml file",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TapnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TapnError::Firing(error) => Some(error),
+            TapnError::Sampling { error, .. } => Some(error),
+            TapnError::Invalid(error) => Some(error),
+            TapnError::Tapaal(error) => Some(error),
+            TapnError::Json(error) => Some(error),
+            TapnError::Query(error) => Some(error),
+            TapnError::Parameter(error) => Some(error),
+            TapnError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+impl From<FiringError> for TapnError {
+    fn from(error: FiringError) -> Self {
+        TapnError::Firing(error)
+    }
+}
+
+impl From<ValidationError> for TapnError {
+    fn from(error: ValidationError) -> Self {
+        TapnError::Invalid(error)
+    }
+}
+
+impl From<TapaalError> for TapnError {
+    fn from(error: TapaalError) -> Self {
+        TapnError::Tapaal(error)
+    }
+}
+
+impl From<JsonError> for TapnError {
+    fn from(error: JsonError) -> Self {
+        TapnError::Json(error)
+    }
+}
+
+impl From<QueryError> for TapnError {
+    fn from(error: QueryError) -> Self {
+        TapnError::Query(error)
+    }
+}
+
+impl From<ParameterError> for TapnError {
+    fn from(error: ParameterError) -> Self {
+        TapnError::Parameter(error)
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Write;
+
+use crate::{ArcType, OutputArc, Tapn};
+
+/// Token ages listed in a place before the rest are summarized
+const SHOWN_TOKENS: usize = 6;
+
+/// Options for `Tapn::to_dot_with`
+#[derive(Clone, Debug, Default)]
+pub struct DotOptions {
+    /// Fills the transitions that are enabled in the current marking
+    pub highlight_enabled: bool,
+}
+
+impl DotOptions {
+    pub fn with_highlight_enabled(mut self, highlight_enabled: bool) -> Self {
+        self.highlight_enabled = highlight_enabled;
+        self
+    }
+}
+
+/// Quotes a label, escaping what DOT reserves in strings
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn interval(timing: &[f64; 2]) -> String {
+    if timing[1].is_infinite() {
+        format!("[{}, ∞)", timing[0])
+    } else {
+        format!("[{}, {}]", timing[0], timing[1])
+    }
+}
+
+/// Arc label with the weight in front when more than one token moves
+fn weighted(weight: usize, label: String) -> String {
+    match (weight, label.is_empty()) {
+        (1, _) => label,
+        (_, true) => weight.to_string(),
+        (_, false) => format!("{weight} × {label}"),
+    }
+}
+
+impl Tapn {
+    /// Renders the net and its current marking as a Graphviz DOT graph
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+
+    /// Renders the net like `to_dot`, styled according to `options`
+    pub fn to_dot_with(&self, options: &DotOptions) -> String {
+        let mut dot = String::from("digraph tapn {\n    rankdir=LR;\n");
+
+        for (index, place) in self.places.iter().enumerate() {
+            let tokens = self.marking.tokens(index);
+            let mut ages: Vec<_> = tokens
+                .iter()
+                .take(SHOWN_TOKENS)
+                .map(|age| format!("{age:.2}"))
+                .collect();
+            if tokens.len() > SHOWN_TOKENS {
+                ages.push(format!("+{} more", tokens.len() - SHOWN_TOKENS));
+            }
+
+            let mut label = format!("{}\n{{{}}}", place.name, ages.join(", "));
+            for invariant in &place.invariants {
+                let operator = if invariant.strict { "<" } else { "≤" };
+                write!(label, "\nage {operator} {}", invariant.bound).unwrap();
+            }
+
+            writeln!(dot, "    p{index} [shape=circle, label={}];", quote(&label)).unwrap();
+        }
+
+        for (index, transition) in self.transitions.iter().enumerate() {
+            let mut label = format!("T{index}\n{}", transition.distribution_function);
+            let mut style = vec!["shape=box".to_string()];
+            if transition.urgent {
+                label += "\nurgent";
+                style.push("peripheries=2".to_string());
+            }
+            if options.highlight_enabled && transition.is_enabled(&self.marking, &self.places) {
+                style.push("style=filled, fillcolor=palegreen".to_string());
+            }
+
+            writeln!(
+                dot,
+                "    t{index} [{}, label={}];",
+                style.join(", "),
+                quote(&label)
+            )
+            .unwrap();
+        }
+
+        for (index, transition) in self.transitions.iter().enumerate() {
+            for arc in &transition.input_arcs {
+                let (place, label, style) = match arc {
+                    ArcType::Input(arc) => (
+                        arc.input,
+                        weighted(arc.weight, interval(&arc.timing)),
+                        "",
+                    ),
+                    ArcType::Transport(arc) => (
+                        arc.input,
+                        weighted(arc.weight, format!("{} :{}", interval(&arc.timing), arc.pair)),
+                        ", style=dashed, arrowhead=diamond",
+                    ),
+                    // Inhibitors block once the place holds `constraint` tokens
+                    ArcType::Inhibitor(arc) => (
+                        arc.input,
+                        format!("≥ {}", arc.constraint),
+                        ", arrowhead=odot",
+                    ),
+                };
+                writeln!(dot, "    p{place} -> t{index} [label={}{style}];", quote(&label))
+                    .unwrap();
+            }
+
+            for arc in &transition.output_arcs {
+                let (place, label, style) = match arc {
+                    OutputArc::Regular(arc) => (arc.output, weighted(arc.weight, String::new()), ""),
+                    OutputArc::TransportArc(arc) => (
+                        arc.output,
+                        weighted(arc.weight, format!(":{}", arc.pair)),
+                        ", style=dashed, arrowhead=diamond",
+                    ),
+                };
+                writeln!(dot, "    t{index} -> p{place} [label={}{style}];", quote(&label))
+                    .unwrap();
+            }
+        }
+
+        dot += "}\n";
+        dot
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Marking, Place, Tapn, Transition, ValidationError};
+
+/// Version of the JSON model format written by `Tapn::to_json`
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Reason a JSON model could not be loaded or saved
+#[derive(Debug)]
+pub enum JsonError {
+    Io(std::io::Error),
+    /// The text is not valid JSON or doesn't describe a model
+    Parse(serde_json::Error),
+    /// The model was written in a format version this version can't read
+    UnsupportedVersion(u32),
+    /// The model describes a net that fails `Tapn::validate`
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Io(error) => write!(f, "cannot access model: {error}"),
+            JsonError::Parse(error) => write!(f, "invalid model: {error}"),
+            JsonError::UnsupportedVersion(version) => write!(
+                f,
+                "model format version {version} is not supported, expected {FORMAT_VERSION}"
+            ),
+            JsonError::Invalid(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonError::Io(error) => Some(error),
+            JsonError::Parse(error) => Some(error),
+            JsonError::Invalid(error) => Some(error),
+            JsonError::UnsupportedVersion(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for JsonError {
+    fn from(error: std::io::Error) -> Self {
+        JsonError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(error: serde_json::Error) -> Self {
+        JsonError::Parse(error)
+    }
+}
+
+/// Everything needed to rebuild a net, without its simulation state
+#[derive(Serialize, Deserialize)]
+struct Model {
+    format_version: u32,
+    places: Vec<Place>,
+    transitions: Vec<Transition>,
+    initial_marking: Marking,
+}
+
+impl Tapn {
+    /// Describes the net and its initial marking as pretty-printed JSON.
+    /// The simulation state, observers and seed are not part of the model.
+    pub fn to_json(&self) -> String {
+        let model = Model {
+            format_version: FORMAT_VERSION,
+            places: self.places.clone(),
+            transitions: self.transitions.clone(),
+            initial_marking: self.initial_marking.clone(),
+        };
+        serde_json::to_string_pretty(&model).expect("models only contain serializable data")
+    }
+
+    /// Builds a net from a model written by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        let model: Model = serde_json::from_str(json)?;
+        if model.format_version != FORMAT_VERSION {
+            return Err(JsonError::UnsupportedVersion(model.format_version));
+        }
+
+        Tapn::new(model.places, model.transitions, model.initial_marking)
+            .map_err(JsonError::Invalid)
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), JsonError> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    pub fn load_json(path: impl AsRef<Path>) -> Result<Self, JsonError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Serializes an upper bound, writing infinity as `null` since JSON has no infinite numbers
+pub(crate) mod bound {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bound: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        bound.is_finite().then_some(*bound).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+}
+
+/// Serializes a guard `[lower, upper]` as a two element array with an unbounded upper end as
+/// `null`
+pub(crate) mod interval {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(timing: &[f64; 2], serializer: S) -> Result<S::Ok, S::Error> {
+        let upper = timing[1].is_finite().then_some(timing[1]);
+        (timing[0], upper).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 2], D::Error> {
+        let (lower, upper) = <(f64, Option<f64>)>::deserialize(deserializer)?;
+        Ok([lower, upper.unwrap_or(f64::INFINITY)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petri::testing::{places, transfer};
+    use crate::{ArcType, Distribution, Invariant, MemoryPolicy};
+
+    /// Net using the optional parts of the format: an invariant, an unbounded guard,
+    /// a memory policy, a weight, a priority and an urgent transition
+    fn net() -> Tapn {
+        let mut places = places(2);
+        places[0].invariants.push(Invariant::less_than(4.0));
+        let mut guarded = transfer(0, 1, Distribution::Exponential(2.0), 0)
+            .with_memory_policy(MemoryPolicy::AgeMemory)
+            .with_weight(3.0)
+            .with_priority(1);
+        if let ArcType::Input(arc) = &mut guarded.input_arcs[0] {
+            arc.timing = [1.0, f64::INFINITY];
+        }
+        let mut urgent = transfer(1, 0, Distribution::Constant(1.0), 1);
+        urgent.urgent = true;
+        let marking = Marking::new(vec![vec![0.0, 2.5], Vec::new()]);
+        Tapn::new(places, vec![guarded, urgent], marking).unwrap()
+    }
+
+    #[test]
+    fn models_read_back_unchanged() {
+        let json = net().to_json();
+        assert_eq!(Tapn::from_json(&json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn infinite_bounds_are_written_as_null() {
+        let json = net().to_json();
+        let model: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let timing = &model["transitions"][0]["input_arcs"][0]["input"]["timing"];
+        assert_eq!(*timing, serde_json::json!([1.0, null]));
+
+        let tapn = Tapn::from_json(&json).unwrap();
+        match &tapn.transitions[0].input_arcs[0] {
+            ArcType::Input(arc) => assert_eq!(arc.timing, [1.0, f64::INFINITY]),
+            arc => panic!("read back {arc:?}"),
+        }
+    }
+
+    #[test]
+    fn other_format_versions_are_rejected() {
+        let json = net().to_json().replacen(
+            &format!("\"format_version\": {FORMAT_VERSION}"),
+            "\"format_version\": 99",
+            1,
+        );
+        match Tapn::from_json(&json) {
+            Err(JsonError::UnsupportedVersion(99)) => {}
+            Err(error) => panic!("{error}"),
+            Ok(_) => panic!("version 99 was accepted"),
+        }
+    }
+}
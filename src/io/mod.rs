@@ -0,0 +1,24 @@
+pub mod dot;
+pub mod json;
+pub mod tapaal;
+
+pub use dot::DotOptions;
+pub use json::{FORMAT_VERSION, JsonError};
+pub use tapaal::{Location, TapaalError};
+
+use std::path::Path;
+
+use crate::{Tapn, TapnError};
+
+impl Tapn {
+    /// Loads a model, choosing between JSON and TAPAAL XML by the file extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TapnError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match extension.to_lowercase().as_str() {
+            "json" => Ok(Self::load_json(path)?),
+            "tapn" | "xml" | "pnml" => Ok(Self::load_tapaal(path)?),
+            _ => Err(TapnError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+}
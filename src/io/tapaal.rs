@@ -0,0 +1,1031 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    ArcType, Distribution, DistributionError, InhibitorArc, InputArc, Invariant, Marking,
+    MemoryPolicy, OutputArc, Place, RegularOutputArc, Tapn, TokenSelection, Transition, TransportArc,
+    TransportOutputArc, ValidationError,
+};
+
+/// Position of an element in a TAPAAL file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+    /// Tag of the element and its ID, such as ``arc `P0 to T0` ``
+    pub element: String,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.element, self.line, self.column)
+    }
+}
+
+/// Reason a TAPAAL model could not be imported or exported
+#[derive(Debug)]
+pub enum TapaalError {
+    Io(std::io::Error),
+    /// The file is not well-formed XML
+    Xml(roxmltree::Error),
+    /// The file has no active `net` element
+    NoNet,
+    MissingAttribute {
+        location: Location,
+        attribute: &'static str,
+    },
+    InvalidValue {
+        location: Location,
+        attribute: &'static str,
+        value: String,
+    },
+    /// An arc names a place or transition that doesn't exist
+    UnknownNode { location: Location, id: String },
+    /// Another place or transition already has this ID
+    DuplicateId { location: Location, id: String },
+    /// An arc of this type can't connect its source and target this way
+    InvalidArc { location: Location, reason: String },
+    /// The halves of the transport arcs with this pairing number don't match up
+    UnpairedTransport { location: Location, pair: usize },
+    InvalidDistribution {
+        location: Location,
+        error: DistributionError,
+    },
+    /// A TAPAAL feature this simulator can't represent
+    Unsupported { location: Location, construct: String },
+    /// The imported net fails `Tapn::validate`
+    Invalid(ValidationError),
+    /// A part of the net TAPAAL can't represent, found while exporting
+    Unrepresentable { element: String, construct: String },
+}
+
+impl fmt::Display for TapaalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapaalError::Io(error) => write!(f, "cannot read model: {error}"),
+            TapaalError::Xml(error) => write!(f, "malformed XML: {error}"),
+            TapaalError::NoNet => write!(f, "the model doesn't contain an active net"),
+            TapaalError::MissingAttribute {
+                location,
+                attribute,
+            } => write!(f, "{location}: missing attribute `{attribute}`"),
+            TapaalError::InvalidValue {
+                location,
+                attribute,
+                value,
+            } => write!(f, "{location}: invalid {attribute} `{value}`"),
+            TapaalError::UnknownNode { location, id } => {
+                write!(f, "{location}: no place or transition with ID `{id}`")
+            }
+            TapaalError::DuplicateId { location, id } => {
+                write!(f, "{location}: ID `{id}` is already in use")
+            }
+            TapaalError::InvalidArc { location, reason } => write!(f, "{location}: {reason}"),
+            TapaalError::UnpairedTransport { location, pair } => write!(
+                f,
+                "{location}: transport arcs with pairing number {pair} don't form one input and output pair"
+            ),
+            TapaalError::InvalidDistribution { location, error } => {
+                write!(f, "{location}: {error}")
+            }
+            TapaalError::Unsupported {
+                location,
+                construct,
+            } => write!(f, "{location}: {construct} is not supported"),
+            TapaalError::Invalid(error) => write!(f, "{error}"),
+            TapaalError::Unrepresentable { element, construct } => {
+                write!(f, "{element}: {construct} can't be written to TAPAAL")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TapaalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TapaalError::Io(error) => Some(error),
+            TapaalError::Xml(error) => Some(error),
+            TapaalError::InvalidDistribution { error, .. } => Some(error),
+            TapaalError::Invalid(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TapaalError {
+    fn from(error: std::io::Error) -> Self {
+        TapaalError::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for TapaalError {
+    fn from(error: roxmltree::Error) -> Self {
+        TapaalError::Xml(error)
+    }
+}
+
+/// Arc guard with an optional transport pairing number, like `[0,5]:1`,
+/// or a plain weight as in PNML
+struct Inscription {
+    timing: [f64; 2],
+    pair: Option<usize>,
+    weight: Option<usize>,
+}
+
+/// Input or output half of a transport arc, kept to check that the halves match up
+struct TransportHalf<'a> {
+    node: Node<'a, 'a>,
+    transition: usize,
+    pair: usize,
+    weight: usize,
+}
+
+struct Importer<'a> {
+    document: &'a Document<'a>,
+    /// Values of the `constant` elements, usable in guards and invariants
+    constants: HashMap<String, f64>,
+}
+
+impl<'a> Importer<'a> {
+    fn location(&self, node: Node) -> Location {
+        let position = self.document.text_pos_at(node.range().start);
+        let tag = node.tag_name().name();
+        let element = match node.attribute("id").or(node.attribute("name")) {
+            Some(id) => format!("{tag} `{id}`"),
+            None => tag.to_string(),
+        };
+
+        Location {
+            line: position.row,
+            column: position.col,
+            element,
+        }
+    }
+
+    fn unsupported(&self, node: Node, construct: impl Into<String>) -> TapaalError {
+        TapaalError::Unsupported {
+            location: self.location(node),
+            construct: construct.into(),
+        }
+    }
+
+    fn invalid(&self, node: Node, attribute: &'static str, value: &str) -> TapaalError {
+        TapaalError::InvalidValue {
+            location: self.location(node),
+            attribute,
+            value: value.to_string(),
+        }
+    }
+
+    /// Value given either as an attribute, as TAPAAL writes it, or as a `<text>` child as in
+    /// plain PNML
+    fn value<'n>(&self, node: Node<'n, 'n>, name: &'static str) -> Option<&'n str> {
+        node.attribute(name).or_else(|| {
+            node.children()
+                .find(|child| child.has_tag_name(name))?
+                .children()
+                .find(|child| child.has_tag_name("text"))?
+                .text()
+                .map(str::trim)
+        })
+    }
+
+    fn required<'n>(&self, node: Node<'n, 'n>, name: &'static str) -> Result<&'n str, TapaalError> {
+        self.value(node, name)
+            .ok_or_else(|| TapaalError::MissingAttribute {
+                location: self.location(node),
+                attribute: name,
+            })
+    }
+
+    fn parse<T: std::str::FromStr>(
+        &self,
+        node: Node,
+        name: &'static str,
+        default: T,
+    ) -> Result<T, TapaalError> {
+        match self.value(node, name) {
+            Some(text) => text.parse().map_err(|_| self.invalid(node, name, text)),
+            None => Ok(default),
+        }
+    }
+
+    /// A number, `inf`, or the name of a constant
+    fn bound(&self, node: Node, attribute: &'static str, text: &str) -> Result<f64, TapaalError> {
+        let text = text.trim();
+        if text == "inf" {
+            return Ok(f64::INFINITY);
+        }
+        if let Some(&value) = self.constants.get(text) {
+            return Ok(value);
+        }
+        text.parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite() && *value >= 0.0)
+            .ok_or_else(|| self.invalid(node, attribute, text))
+    }
+
+    fn place(&self, node: Node) -> Result<(Place, Vec<f64>), TapaalError> {
+        let id = self.required(node, "id")?;
+        let name = self.value(node, "name").unwrap_or(id).to_string();
+
+        let tokens: usize = self.parse(node, "initialMarking", 0)?;
+
+        let mut invariants = Vec::new();
+        if let Some(invariant) = self.value(node, "invariant") {
+            let invariant = invariant.trim();
+            let (strict, bound) = if let Some(bound) = invariant.strip_prefix("<=") {
+                (false, bound)
+            } else if let Some(bound) = invariant.strip_prefix('<') {
+                (true, bound)
+            } else {
+                return Err(self.invalid(node, "invariant", invariant));
+            };
+
+            let bound = self.bound(node, "invariant", bound)?;
+            if bound.is_finite() {
+                invariants.push(if strict {
+                    Invariant::less_than(bound)
+                } else {
+                    Invariant::at_most(bound)
+                });
+            }
+        }
+
+        // The index becomes the ID, TAPAAL IDs are only used to resolve arcs
+        Ok((Place::new(0, name, invariants), vec![0.0; tokens]))
+    }
+
+    fn distribution(&self, node: Node) -> Result<Distribution, TapaalError> {
+        let number = |name| -> Result<f64, TapaalError> {
+            let text = self.required(node, name)?;
+            text.parse().map_err(|_| self.invalid(node, name, text))
+        };
+        let integer = |name| -> Result<u64, TapaalError> {
+            let text = self.required(node, name)?;
+            text.parse().map_err(|_| self.invalid(node, name, text))
+        };
+
+        // Without a distribution a transition fires as soon as it is enabled
+        let Some(kind) = node.attribute("distribution") else {
+            return Ok(Distribution::Constant(0.0));
+        };
+
+        let distribution = match kind.to_lowercase().as_str() {
+            "constant" => Distribution::constant(number("value")?),
+            "uniform" => Distribution::uniform(number("a")?, number("b")?),
+            "exponential" => Distribution::exponential(number("rate")?),
+            "normal" => Distribution::normal(number("mean")?, number("stddev")?),
+            "lognormal" | "log normal" => {
+                Distribution::log_normal(number("logMean")?, number("logStddev")?)
+            }
+            "gamma" => Distribution::gamma(number("shape")?, number("scale")?),
+            "erlang" => {
+                let shape = integer("shape")?;
+                let shape = u32::try_from(shape)
+                    .map_err(|_| self.invalid(node, "shape", &shape.to_string()))?;
+                Distribution::erlang(shape, 1.0 / number("scale")?)
+            }
+            "weibull" => Distribution::weibull(number("scale")?, number("shape")?),
+            "triangular" => Distribution::triangular(number("a")?, number("c")?, number("b")?),
+            "geometric" => Distribution::geometric(number("p")?),
+            "discrete uniform" | "discreteuniform" => {
+                Distribution::discrete_uniform(integer("a")?, integer("b")?)
+            }
+            _ => return Err(self.unsupported(node, format!("distribution `{kind}`"))),
+        };
+
+        distribution.map_err(|error| TapaalError::InvalidDistribution {
+            location: self.location(node),
+            error,
+        })
+    }
+
+    fn transition(&self, node: Node, index: usize) -> Result<Transition, TapaalError> {
+        let urgent: bool = self.parse(node, "urgent", false)?;
+        let priority: u32 = self.parse(node, "priority", 0)?;
+        let weight: f64 = self.parse(node, "weight", 1.0)?;
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(self.invalid(node, "weight", &weight.to_string()));
+        }
+
+        // Written by `to_tapaal`, TAPAAL itself always samples anew once re-enabled
+        let memory_policy = match node.attribute("memoryPolicy") {
+            None | Some("enabling") => MemoryPolicy::EnablingMemory,
+            Some("resampling") => MemoryPolicy::Resampling,
+            Some("age") => MemoryPolicy::AgeMemory,
+            Some(policy) => return Err(self.invalid(node, "memoryPolicy", policy)),
+        };
+
+        Ok(
+            Transition::new(Vec::new(), Vec::new(), self.distribution(node)?, urgent, index)
+                .with_priority(priority)
+                .with_weight(weight)
+                .with_memory_policy(memory_policy),
+        )
+    }
+
+    fn selection(&self, node: Node) -> Result<TokenSelection, TapaalError> {
+        match node.attribute("firingMode") {
+            None | Some("Oldest") => Ok(TokenSelection::OldestEligible),
+            Some("Youngest") => Ok(TokenSelection::YoungestEligible),
+            Some("Random") => Ok(TokenSelection::RandomEligible),
+            Some(mode) => Err(self.invalid(node, "firingMode", mode)),
+        }
+    }
+
+    /// Parses guards like `[0,inf)` or `[2,5]:1`, plain weights like `1` mean no guard
+    fn inscription(&self, node: Node) -> Result<Inscription, TapaalError> {
+        let text = self.value(node, "inscription").unwrap_or("[0,inf)").trim();
+
+        let (guard, pair) = match text.rsplit_once(':') {
+            Some((guard, pair)) => {
+                let pair = pair
+                    .trim()
+                    .parse()
+                    .map_err(|_| self.invalid(node, "inscription", text))?;
+                (guard.trim(), Some(pair))
+            }
+            None => (text, None),
+        };
+
+        let Some(interval) = guard.strip_prefix(['[', '(']) else {
+            let weight = guard
+                .parse()
+                .map_err(|_| self.invalid(node, "inscription", text))?;
+            return Ok(Inscription {
+                timing: [0.0, f64::INFINITY],
+                pair,
+                weight: Some(weight),
+            });
+        };
+
+        let invalid = || self.invalid(node, "inscription", text);
+        let (lower, upper) = interval
+            .strip_suffix([']', ')'])
+            .and_then(|interval| interval.split_once(','))
+            .ok_or_else(invalid)?;
+        let lower_open = guard.starts_with('(');
+        let upper_open = guard.ends_with(')');
+
+        let timing = [
+            self.bound(node, "inscription", lower)?,
+            self.bound(node, "inscription", upper)?,
+        ];
+        if timing[0] > timing[1] {
+            return Err(invalid());
+        }
+        // Guards are closed intervals, only an infinite upper end may be open
+        if lower_open || (upper_open && timing[1].is_finite()) {
+            return Err(self.unsupported(node, format!("open interval bound in `{guard}`")));
+        }
+
+        Ok(Inscription {
+            timing,
+            pair,
+            weight: None,
+        })
+    }
+
+    fn net(&self, net: Node<'a, 'a>) -> Result<Tapn, TapaalError> {
+        let mut places = Vec::new();
+        let mut tokens = Vec::new();
+        let mut transitions = Vec::new();
+        // Token selection of every transition, applied to all its consuming arcs
+        let mut selections = Vec::new();
+        // TAPAAL ID of every place and transition, mapped to its index
+        let mut place_ids = HashMap::new();
+        let mut transition_ids = HashMap::new();
+
+        let members = members(net);
+        for &node in &members {
+            // Arcs tell places and transitions apart by ID only
+            if node.has_tag_name("place") || node.has_tag_name("transition") {
+                let id = self.required(node, "id")?;
+                if place_ids.contains_key(id) || transition_ids.contains_key(id) {
+                    return Err(TapaalError::DuplicateId {
+                        location: self.location(node),
+                        id: id.to_string(),
+                    });
+                }
+            }
+
+            match node.tag_name().name() {
+                "place" => {
+                    let (mut place, initial) = self.place(node)?;
+                    place.id = places.len();
+                    place_ids.insert(self.required(node, "id")?, places.len());
+                    places.push(place);
+                    tokens.push(initial);
+                }
+                "transition" => {
+                    transition_ids.insert(self.required(node, "id")?, transitions.len());
+                    transitions.push(self.transition(node, transitions.len())?);
+                    selections.push(self.selection(node)?);
+                }
+                "arc" | "labels" | "name" | "graphics" | "toolspecific" => {}
+                tag => return Err(self.unsupported(node, format!("element `{tag}`"))),
+            }
+        }
+
+        // Halves of transport arcs in document order
+        let mut transport_inputs = Vec::new();
+        let mut transport_outputs = Vec::new();
+
+        for &node in members.iter().filter(|node| node.has_tag_name("arc")) {
+            let source = self.required(node, "source")?;
+            let target = self.required(node, "target")?;
+            let invalid_arc = |reason: &str| TapaalError::InvalidArc {
+                location: self.location(node),
+                reason: reason.to_string(),
+            };
+
+            for id in [source, target] {
+                if !place_ids.contains_key(id) && !transition_ids.contains_key(id) {
+                    return Err(TapaalError::UnknownNode {
+                        location: self.location(node),
+                        id: id.to_string(),
+                    });
+                }
+            }
+
+            // Exactly one end has to be a place, the other a transition
+            let (place, transition, is_input) = match (place_ids.get(source), place_ids.get(target))
+            {
+                (Some(&place), None) => (place, transition_ids[target], true),
+                (None, Some(&place)) => (place, transition_ids[source], false),
+                _ => return Err(invalid_arc("arcs must connect a place and a transition")),
+            };
+
+            let inscription = self.inscription(node)?;
+            let weight: usize = self.parse(node, "weight", inscription.weight.unwrap_or(1))?;
+            if weight == 0 {
+                return Err(self.invalid(node, "weight", "0"));
+            }
+            let kind = node.attribute("type").unwrap_or("normal");
+
+            match (kind, is_input) {
+                ("timed" | "normal", true) => {
+                    transitions[transition].input_arcs.push(ArcType::Input(InputArc {
+                        input: place,
+                        weight,
+                        timing: inscription.timing,
+                        selection: selections[transition],
+                    }));
+                }
+                ("normal", false) => {
+                    transitions[transition]
+                        .output_arcs
+                        .push(OutputArc::Regular(RegularOutputArc {
+                            output: place,
+                            weight,
+                        }));
+                }
+                ("tapnInhibitor" | "inhibitor", true) => {
+                    // Inhibits once the place holds `weight` tokens
+                    transitions[transition]
+                        .input_arcs
+                        .push(ArcType::Inhibitor(InhibitorArc {
+                            input: place,
+                            weight,
+                            constraint: weight,
+                            timing: inscription.timing,
+                        }));
+                }
+                ("transport", _) => {
+                    let pair = inscription
+                        .pair
+                        .ok_or_else(|| invalid_arc("transport arcs need a pairing number"))?;
+                    let half = TransportHalf {
+                        node,
+                        transition,
+                        pair,
+                        weight,
+                    };
+
+                    // Arcs stay in document order, the pairing is checked once all are known
+                    if is_input {
+                        transport_inputs.push(half);
+                        transitions[transition]
+                            .input_arcs
+                            .push(ArcType::Transport(TransportArc {
+                                input: place,
+                                weight,
+                                timing: inscription.timing,
+                                selection: selections[transition],
+                                pair,
+                            }));
+                    } else {
+                        transport_outputs.push(half);
+                        transitions[transition]
+                            .output_arcs
+                            .push(OutputArc::TransportArc(TransportOutputArc {
+                                output: place,
+                                weight,
+                                pair,
+                            }));
+                    }
+                }
+                ("timed" | "tapnInhibitor" | "inhibitor", false) => {
+                    return Err(invalid_arc(
+                        "only normal and transport arcs can lead to a place",
+                    ));
+                }
+                (kind, _) => return Err(self.unsupported(node, format!("arc type `{kind}`"))),
+            }
+        }
+
+        // Every half needs exactly one counterpart of the same weight in its transition
+        let matching = |half: &TransportHalf, others: &[TransportHalf]| {
+            others
+                .iter()
+                .filter(|other| (other.transition, other.pair) == (half.transition, half.pair))
+                .map(|other| other.weight)
+                .collect::<Vec<_>>()
+                == [half.weight]
+        };
+        let unpaired = transport_inputs
+            .iter()
+            .filter(|half| !matching(half, &transport_outputs))
+            .chain(
+                transport_outputs
+                    .iter()
+                    .filter(|half| !matching(half, &transport_inputs)),
+            )
+            .min_by_key(|half| half.node.range().start);
+        if let Some(half) = unpaired {
+            return Err(TapaalError::UnpairedTransport {
+                location: self.location(half.node),
+                pair: half.pair,
+            });
+        }
+
+        Tapn::new(places, transitions, Marking::new(tokens)).map_err(TapaalError::Invalid)
+    }
+}
+
+/// Child elements of a net, with the contents of PNML pages flattened into it
+fn members<'a>(net: Node<'a, 'a>) -> Vec<Node<'a, 'a>> {
+    net.children()
+        .filter(Node::is_element)
+        .flat_map(|node| {
+            if node.has_tag_name("page") {
+                members(node)
+            } else {
+                vec![node]
+            }
+        })
+        .collect()
+}
+
+impl Tapn {
+    /// Imports the active net of a TAPAAL `.tapn` or PNML file
+    pub fn from_tapaal(xml: &str) -> Result<Self, TapaalError> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+        let mut importer = Importer {
+            document: &document,
+            constants: HashMap::new(),
+        };
+
+        let mut nets = Vec::new();
+        for node in root.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "net" if node.attribute("active") != Some("false") => nets.push(node),
+                "constant" => {
+                    let name = importer.required(node, "name")?;
+                    let value = importer.required(node, "value")?;
+                    let value = importer.bound(node, "value", value)?;
+                    importer.constants.insert(name.to_string(), value);
+                }
+                "feature" if node.attribute("isColored") == Some("true") => {
+                    return Err(importer.unsupported(node, "a colored net"));
+                }
+                "shared-place" | "shared-transition" => {
+                    return Err(importer.unsupported(node, "sharing between components"));
+                }
+                // Queries, bounds and inactive components don't affect the net
+                _ => {}
+            }
+        }
+
+        match nets[..] {
+            [] => Err(TapaalError::NoNet),
+            [net] => importer.net(net),
+            [_, second, ..] => Err(importer.unsupported(second, "more than one active component")),
+        }
+    }
+
+    /// Reads and imports a TAPAAL `.tapn` or PNML file
+    pub fn load_tapaal(path: impl AsRef<Path>) -> Result<Self, TapaalError> {
+        Self::from_tapaal(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Writes attribute values with the characters XML reserves escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn guard(timing: &[f64; 2]) -> String {
+    if timing[1].is_infinite() {
+        format!("[{},inf)", timing[0])
+    } else {
+        format!("[{},{}]", timing[0], timing[1])
+    }
+}
+
+/// TAPAAL attributes describing a distribution, named like the importer reads them
+fn distribution_attributes(distribution: &Distribution) -> Option<String> {
+    let attributes = match distribution {
+        Distribution::Constant(value) => format!(r#"distribution="constant" value="{value}""#),
+        Distribution::Uniform(a, b) => format!(r#"distribution="uniform" a="{a}" b="{b}""#),
+        Distribution::Exponential(rate) => format!(r#"distribution="exponential" rate="{rate}""#),
+        Distribution::Normal(mean, std_dev) => {
+            format!(r#"distribution="normal" mean="{mean}" stddev="{std_dev}""#)
+        }
+        Distribution::LogNormal(mu, sigma) => {
+            format!(r#"distribution="lognormal" logMean="{mu}" logStddev="{sigma}""#)
+        }
+        Distribution::Gamma(shape, scale) => {
+            format!(r#"distribution="gamma" shape="{shape}" scale="{scale}""#)
+        }
+        Distribution::Erlang(k, rate) => {
+            format!(r#"distribution="erlang" shape="{k}" scale="{}""#, 1.0 / rate)
+        }
+        Distribution::Weibull(scale, shape) => {
+            format!(r#"distribution="weibull" scale="{scale}" shape="{shape}""#)
+        }
+        Distribution::Triangular(min, mode, max) => {
+            format!(r#"distribution="triangular" a="{min}" b="{max}" c="{mode}""#)
+        }
+        Distribution::Geometric(p) => format!(r#"distribution="geometric" p="{p}""#),
+        Distribution::DiscreteUniform(a, b) => {
+            format!(r#"distribution="discrete uniform" a="{a}" b="{b}""#)
+        }
+        Distribution::Empirical { .. } => return None,
+    };
+    Some(attributes)
+}
+
+impl Tapn {
+    /// Writes the net in TAPAAL's XML format, starting from its initial marking.
+    /// Places keep their names where they are unique TAPAAL identifiers, transitions are
+    /// named `T` followed by their index, and nodes are laid out on a simple grid.
+    pub fn to_tapaal(&self) -> Result<String, TapaalError> {
+        let unrepresentable = |element: String, construct: &str| TapaalError::Unrepresentable {
+            element,
+            construct: construct.to_string(),
+        };
+
+        // Places and transitions share one namespace of IDs. Transitions are `T{index}`,
+        // places keep their name if it's a free identifier and get a fresh one otherwise.
+        let mut used: HashSet<String> = (0..self.transitions.len())
+            .map(|index| format!("T{index}"))
+            .collect();
+        let kept: Vec<bool> = self
+            .places
+            .iter()
+            .map(|place| is_identifier(&place.name) && used.insert(place.name.clone()))
+            .collect();
+        let mut place_names = Vec::new();
+        for (index, place) in self.places.iter().enumerate() {
+            if kept[index] {
+                place_names.push(place.name.clone());
+                continue;
+            }
+            let mut name = format!("P{index}");
+            for n in 1.. {
+                if used.insert(name.clone()) {
+                    break;
+                }
+                name = format!("P{index}_{n}");
+            }
+            place_names.push(name);
+        }
+
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#,
+            "\n",
+            r#"<pnml xmlns="http://www.informatik.hu-berlin.de/top/pnml/ptNetb">"#,
+            "\n",
+            r#"  <net active="true" id="TAPN1" type="P/T net">"#,
+            "\n",
+        ));
+
+        for (index, place) in self.places.iter().enumerate() {
+            let name = escape(&place_names[index]);
+            let tokens = self.initial_marking.tokens(index);
+            if tokens.iter().any(|&age| age != 0.0) {
+                return Err(unrepresentable(
+                    format!("place `{name}`"),
+                    "initial tokens older than 0",
+                ));
+            }
+
+            // TAPAAL has one invariant per place, the tightest one is equivalent to all of them
+            let invariant = place
+                .invariants
+                .iter()
+                .min_by(|a, b| a.bound.total_cmp(&b.bound).then(b.strict.cmp(&a.strict)))
+                .map_or("&lt; inf".to_string(), |invariant| {
+                    let operator = if invariant.strict { "&lt;" } else { "&lt;=" };
+                    format!("{operator} {}", invariant.bound)
+                });
+
+            xml += &format!(
+                r#"    <place displayName="true" id="{name}" initialMarking="{}" invariant="{invariant}" name="{name}" nameOffsetX="0" nameOffsetY="0" positionX="{}" positionY="100"/>"#,
+                tokens.len(),
+                100 + 150 * index,
+            );
+            xml.push('\n');
+        }
+
+        for (index, transition) in self.transitions.iter().enumerate() {
+            let name = format!("T{index}");
+            let element = || format!("transition `{name}`");
+            let distribution = distribution_attributes(&transition.distribution_function)
+                .ok_or_else(|| unrepresentable(element(), "an empirical distribution"))?;
+
+            // TAPAAL picks tokens per transition, so all consuming arcs have to agree
+            let mut selections = transition.input_arcs.iter().filter_map(|arc| match arc {
+                ArcType::Input(arc) => Some(arc.selection),
+                ArcType::Transport(arc) => Some(arc.selection),
+                ArcType::Inhibitor(_) => None,
+            });
+            let selection = selections.next().unwrap_or_default();
+            if selections.any(|other| other != selection) {
+                return Err(unrepresentable(element(), "arcs with different token selections"));
+            }
+            let firing_mode = match selection {
+                TokenSelection::OldestEligible => "Oldest",
+                TokenSelection::YoungestEligible => "Youngest",
+                TokenSelection::RandomEligible => "Random",
+                TokenSelection::Fifo => {
+                    return Err(unrepresentable(element(), "FIFO token selection"));
+                }
+            };
+
+            // Not part of TAPAAL, only written when it differs from the default
+            let memory_policy = match transition.memory_policy {
+                MemoryPolicy::EnablingMemory => "",
+                MemoryPolicy::Resampling => r#" memoryPolicy="resampling""#,
+                MemoryPolicy::AgeMemory => r#" memoryPolicy="age""#,
+            };
+
+            xml += &format!(
+                r#"    <transition angle="0" displayName="true" id="{name}" infiniteServer="false" name="{name}" nameOffsetX="0" nameOffsetY="0" player="0" positionX="{}" positionY="300" priority="{}" urgent="{}" weight="{}" firingMode="{firing_mode}" {distribution}{memory_policy}/>"#,
+                100 + 150 * index,
+                transition.priority,
+                transition.urgent,
+                transition.weight,
+            );
+            xml.push('\n');
+        }
+
+        let mut arc_ids = HashSet::new();
+        for (index, transition) in self.transitions.iter().enumerate() {
+            let name = format!("T{index}");
+            let mut arc = |source: &str, target: &str, kind: &str, inscription: String, weight| {
+                // Parallel arcs, such as an input and a transport arc, need distinct IDs
+                let mut id = format!("{source} to {target}");
+                let mut copy = 1;
+                while !arc_ids.insert(id.clone()) {
+                    copy += 1;
+                    id = format!("{source} to {target} {copy}");
+                }
+
+                xml += &format!(
+                    r#"    <arc id="{id}" inscription="{inscription}" nameOffsetX="0" nameOffsetY="0" source="{source}" target="{target}" type="{kind}" weight="{weight}"/>"#,
+                );
+                xml.push('\n');
+            };
+
+            for input in &transition.input_arcs {
+                match input {
+                    ArcType::Input(a) => {
+                        arc(&place_names[a.input], &name, "timed", guard(&a.timing), a.weight)
+                    }
+                    ArcType::Transport(a) => {
+                        let inscription = format!("{}:{}", guard(&a.timing), a.pair);
+                        arc(&place_names[a.input], &name, "transport", inscription, a.weight)
+                    }
+                    // TAPAAL inhibitor arcs block once the place holds their weight in tokens
+                    ArcType::Inhibitor(a) => arc(
+                        &place_names[a.input],
+                        &name,
+                        "tapnInhibitor",
+                        guard(&a.timing),
+                        a.constraint,
+                    ),
+                }
+            }
+
+            for output in &transition.output_arcs {
+                match output {
+                    OutputArc::Regular(a) => {
+                        arc(&name, &place_names[a.output], "normal", "1".to_string(), a.weight)
+                    }
+                    OutputArc::TransportArc(a) => {
+                        // Both halves carry the guard of the input half
+                        let timing = transition
+                            .input_arcs
+                            .iter()
+                            .find_map(|input| match input {
+                                ArcType::Transport(input) if input.pair == a.pair => {
+                                    Some(input.timing)
+                                }
+                                _ => None,
+                            })
+                            .unwrap_or([0.0, f64::INFINITY]);
+                        let inscription = format!("{}:{}", guard(&timing), a.pair);
+                        arc(&name, &place_names[a.output], "transport", inscription, a.weight)
+                    }
+                }
+            }
+        }
+
+        xml += "  </net>\n";
+        xml += "  <feature isGame=\"false\" isTimed=\"true\"/>\n";
+        xml += "</pnml>\n";
+        Ok(xml)
+    }
+
+    /// Writes the net to a TAPAAL `.tapn` file
+    pub fn save_tapaal(&self, path: impl AsRef<Path>) -> Result<(), TapaalError> {
+        std::fs::write(path, self.to_tapaal()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Net with one timed input arc carrying `inscription`
+    fn net_with_inscription(inscription: &str) -> String {
+        format!(
+            r#"<pnml>
+  <net active="true" id="net">
+    <place id="p" name="p" initialMarking="1"/>
+    <transition id="t" name="t" distribution="constant" value="1"/>
+    <arc id="a" inscription="{inscription}" source="p" target="t" type="timed" weight="1"/>
+  </net>
+</pnml>"#
+        )
+    }
+
+    #[test]
+    fn malformed_inscriptions_are_reported_at_their_arc() {
+        for inscription in ["[", "(", "[)", "[0,é", "[0,1", "[01]", "[0,1]x", "[x,1]"] {
+            match Tapn::from_tapaal(&net_with_inscription(inscription)) {
+                Err(TapaalError::InvalidValue {
+                    location,
+                    attribute: "inscription",
+                    ..
+                }) => assert_eq!((location.element.as_str(), location.line), ("arc `a`", 5)),
+                Err(error) => panic!("{inscription}: {error}"),
+                Ok(_) => panic!("{inscription} was accepted"),
+            }
+        }
+    }
+
+    #[test]
+    fn inscriptions_give_guards_and_weights() {
+        let tapn = Tapn::from_tapaal(&net_with_inscription("[0.5,inf)")).unwrap();
+        let ArcType::Input(arc) = &tapn.transitions[0].input_arcs[0] else {
+            panic!("expected an input arc");
+        };
+        assert_eq!(arc.timing, [0.5, f64::INFINITY]);
+        assert!(Tapn::from_tapaal(&net_with_inscription("2")).is_ok());
+    }
+
+    #[test]
+    fn places_and_transitions_need_distinct_ids() {
+        // Added after the transition `t`, on line 5
+        for (duplicate, expected) in [
+            (r#"<place id="p" name="q"/>"#, "p"),
+            (r#"<transition id="p" name="q"/>"#, "p"),
+            (r#"<place id="t" name="q"/>"#, "t"),
+        ] {
+            let xml = net_with_inscription("1").replace("<arc", &format!("{duplicate}\n    <arc"));
+            match Tapn::from_tapaal(&xml) {
+                Err(TapaalError::DuplicateId { location, id }) => {
+                    assert_eq!((location.line, id.as_str()), (5, expected));
+                }
+                Err(error) => panic!("{duplicate}: {error}"),
+                Ok(_) => panic!("{duplicate} was accepted"),
+            }
+        }
+    }
+
+    /// Moves a token from `start` to `middle` and two from `middle` to `end`, unless `block`
+    /// holds two tokens
+    fn transition(id: usize, distribution: Distribution) -> Transition {
+        let mut transition = Transition::new(
+            vec![
+                ArcType::Transport(TransportArc {
+                    input: 0,
+                    weight: 1,
+                    timing: [id as f64, f64::INFINITY],
+                    selection: TokenSelection::default(),
+                    pair: 0,
+                }),
+                ArcType::Input(InputArc {
+                    input: 1,
+                    weight: 2,
+                    timing: [0.0, id as f64 + 1.5],
+                    selection: TokenSelection::default(),
+                }),
+                ArcType::Inhibitor(InhibitorArc {
+                    input: 3,
+                    weight: 2,
+                    constraint: 2,
+                    timing: [0.0, f64::INFINITY],
+                }),
+            ],
+            vec![
+                OutputArc::TransportArc(TransportOutputArc {
+                    output: 1,
+                    weight: 1,
+                    pair: 0,
+                }),
+                OutputArc::Regular(RegularOutputArc {
+                    output: 2,
+                    weight: 1,
+                }),
+            ],
+            distribution,
+            false,
+            id,
+        );
+        transition.priority = id as u32 % 3;
+        transition
+    }
+
+    #[test]
+    fn exported_nets_import_unchanged() {
+        let invariant = |bound, strict| Invariant { bound, strict };
+        // Place 1 falls back to `P1`, which place 5 already uses, and place 4 is named like
+        // the first transition
+        let places = vec![
+            Place::new(0, "start".to_string(), vec![invariant(5.0, false)]),
+            Place::new(1, "middle place".to_string(), vec![invariant(3.0, true)]),
+            Place::new(2, "end".to_string(), Vec::new()),
+            Place::new(3, "block".to_string(), Vec::new()),
+            Place::new(4, "T0".to_string(), Vec::new()),
+            Place::new(5, "P1".to_string(), Vec::new()),
+        ];
+        let distributions = [
+            Distribution::Constant(1.0),
+            Distribution::Uniform(0.5, 2.0),
+            Distribution::Exponential(1.5),
+            Distribution::Normal(2.0, 0.5),
+            Distribution::LogNormal(0.1, 0.2),
+            Distribution::Gamma(2.0, 3.0),
+            Distribution::Erlang(3, 0.5),
+            Distribution::Weibull(1.0, 2.5),
+            Distribution::Triangular(0.0, 1.0, 4.0),
+            Distribution::Geometric(0.25),
+            Distribution::DiscreteUniform(1, 6),
+        ];
+        let mut transitions: Vec<_> = distributions
+            .into_iter()
+            .enumerate()
+            .map(|(id, distribution)| transition(id, distribution))
+            .collect();
+        transitions[0].urgent = true;
+        let marking = Marking::new(vec![
+            vec![0.0; 2],
+            vec![0.0; 3],
+            Vec::new(),
+            vec![0.0],
+            vec![0.0],
+            Vec::new(),
+        ]);
+        let mut tapn = Tapn::new(places, transitions, marking).unwrap();
+
+        let imported = Tapn::from_tapaal(&tapn.to_tapaal().unwrap()).unwrap();
+        let names: Vec<_> = imported.places.iter().map(|place| &place.name).collect();
+        assert_eq!(names, ["start", "P1_1", "end", "block", "P4", "P1"]);
+
+        for (place, name) in tapn.places.iter_mut().zip(names) {
+            place.name.clone_from(name);
+        }
+        assert_eq!(imported.to_json(), tapn.to_json());
+    }
+}
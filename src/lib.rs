@@ -1,11 +1,29 @@
+pub mod error;
+pub mod io;
 pub mod observer;
 pub mod petri;
+pub mod query;
+pub mod simulation;
+
+pub use error::TapnError;
+
+pub use io::{DotOptions, FORMAT_VERSION, JsonError, Location, TapaalError};
 
 pub use petri::{
-    ArcType, InhibitorArc, InputArc, Invariant, OutputArc, Place, RegularOutputArc, Tapn,
-    Transition, TransportArc, TransportOutputArc, Distribution
+    ArcType, Binding, Diagnostic, DiagnosticKind, Distribution, DistributionError, FiringError,
+    InhibitorArc, InputArc, Invariant, Marking, MemoryPolicy, NetElement, OutputArc, Place,
+    RegularOutputArc, RunOutcome, RunResult, Sampler, SimulationRng, StepOutcome, Tapn,
+    TokenSelection, Transition, TransportArc, TransportOutputArc, ValidationError, derive_seed,
 };
 
 pub use observer::{
-    Comparison, SimulationEvent, SimulationObserver, TokenAgeObserver, TokenCoutObserver,
+    Comparison, QueryObserver, SimulationEvent, SimulationObserver, TokenAgeObserver,
+    TokenCoutObserver,
+};
+
+pub use query::{Formula, Quantifier, Query, QueryError, Term};
+
+pub use simulation::{
+    Batch, BatchResult, Bound, Decision, Estimate, Estimator, HypothesisTest, ParameterError,
+    PlaceCondition, Property, RunCheck, Verdict, clopper_pearson,
 };
@@ -1,93 +1,445 @@
-use std::{cell::RefCell, rc::Rc};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::json;
 
 use tapn::{
-    ArcType, Comparison, Distribution, InputArc, OutputArc, Place, RegularOutputArc, 
-    Tapn, TokenAgeObserver, TokenCoutObserver, Transition, TransportArc, TransportOutputArc
+    Batch, Bound, Decision, DotOptions, Estimator, HypothesisTest, Query, RunOutcome,
+    SimulationObserver, Tapn, TapnError,
 };
 
-fn main() {
-    let num_simulations = 100000; // Number of simulations to run
-    let mut total_steps = 0;
-    let mut total_firings = 0;
-
-    for _ in 0..num_simulations {
-        // Create places with unique IDs
-        let accumulated_time = Rc::new(RefCell::new(Place::new(
-            0,  // ID 0
-            "accumulated_time".to_string(),
-            vec![0.0],  // Start with one token at age 0.0
-            vec![],
-        )));
-        
-        let finished = Rc::new(RefCell::new(Place::new(
-            1,  // ID 1
-            "finished".to_string(),
-            vec![],  // Start empty
-            vec![],
-        )));
-
-        // Create delay transition (non-urgent)
-        let delay = Transition::new(
-            vec![ArcType::Transport(TransportArc {
-                input: Rc::clone(&accumulated_time),
-                weight: 1,
-                timing: [0.0, f64::INFINITY],
-            })],
-            vec![OutputArc::TransportArc(TransportOutputArc {
-                output: Rc::clone(&accumulated_time),
-                weight: 1,
-            })],
-            Distribution::Uniform(0.0, 1.0),
-            false,
-            0,  // Transition ID 0
-        );
-
-        // Create timeout transition (should be urgent)
-        let timeout = Transition::new(
-            vec![ArcType::Input(InputArc {
-                input: Rc::clone(&accumulated_time),
-                weight: 1,
-                timing: [1.0, f64::INFINITY],  // Only fire when token age ≥1.0
-            })],
-            vec![OutputArc::Regular(RegularOutputArc {
-                output: Rc::clone(&finished),
-                weight: 1,
-            })],
-            Distribution::Constant(0.0),
-            false,  // This should be urgent!
-            1,  // Transition ID 1
-        );
-
-        // Create and configure observers
-        let count_observer = Box::new(TokenCoutObserver::new()
-            .monitor_place(1, 1, Comparison::Equal));  // Stop when place 1 (finished) has exactly 1 token
-            
-        let age_observer = Box::new(TokenAgeObserver::new(10.0));  // Emergency stop if any token age > 10.0
-
-        // Create TAPN model
-        let mut euler = Tapn::new(
-            vec![accumulated_time, finished],
-            vec![delay, timeout],
-        );
-
-        // Add observers
-        euler.add_observer(count_observer);
-        euler.add_observer(age_observer);
-
-        // Run the simulation until an observer stops it
-        euler.run();
-
-        // Accumulate results
-        total_steps += euler.steps;
-        total_firings += euler.firing_count(0); // Count of delay transitions fired
+/// Simulates and checks timed-arc Petri nets stored as JSON or TAPAAL XML
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs many simulations and prints summary statistics
+    Simulate {
+        #[command(flatten)]
+        model: ModelArgs,
+        /// Query whose answer stops each run, such as `PF<=10 (finished >= 1)`. Without a
+        /// `PF` or `PG` query, runs need a time or step limit.
+        #[arg(short, long)]
+        query: Option<String>,
+        #[command(flatten)]
+        runs: RunArgs,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Estimates the probability of a query, or tests it against a threshold
+    Check {
+        #[command(flatten)]
+        model: ModelArgs,
+        /// Query to check, such as `PF<=10 (finished >= 1)`. `EF` and `AG` queries need a
+        /// time or step limit, runs it ends before they are answered count as inconclusive.
+        #[arg(short, long)]
+        query: String,
+        /// Confidence of the estimated interval
+        #[arg(long, default_value_t = 0.95)]
+        confidence: f64,
+        /// Largest accepted error of the estimate
+        #[arg(long, default_value_t = 0.01)]
+        error: f64,
+        #[arg(long, value_enum, default_value_t = BoundArg::ChernoffHoeffding)]
+        bound: BoundArg,
+        /// Test whether the probability is at least this value instead of estimating it
+        #[arg(long)]
+        threshold: Option<f64>,
+        /// Half-width of the indifference region around the threshold
+        #[arg(long, default_value_t = 0.01)]
+        indifference: f64,
+        /// Probability of wrongly deciding the probability is below the threshold
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        /// Probability of wrongly deciding the probability is at least the threshold
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+        #[command(flatten)]
+        limits: LimitArgs,
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(long)]
+        threads: Option<usize>,
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+    /// Converts the model to another format
+    Export {
+        #[command(flatten)]
+        model: ModelArgs,
+        #[arg(long = "to", value_enum)]
+        to: ExportFormat,
+        /// File to write to, standard output if missing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Fills the transitions enabled in the initial marking of a DOT graph
+        #[arg(long)]
+        highlight_enabled: bool,
+    },
+    /// Loads the model and reports whether it is well-formed
+    Validate {
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+}
+
+#[derive(Args)]
+struct ModelArgs {
+    /// Model file, `.json` or TAPAAL `.tapn`, `.xml` or `.pnml`
+    model: PathBuf,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    runs: usize,
+    /// Base seed the seed of every run is derived from, random if missing
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Worker threads, one per core if missing
+    #[arg(long)]
+    threads: Option<usize>,
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+#[derive(Args)]
+struct LimitArgs {
+    /// Global time at which every run stops
+    #[arg(long)]
+    time_limit: Option<f64>,
+    /// Number of firings after which every run stops
+    #[arg(long)]
+    step_limit: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BoundArg {
+    ChernoffHoeffding,
+    ClopperPearson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Dot,
+    Pnml,
+    Json,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Simulate {
+            model,
+            query,
+            runs,
+            format,
+        } => simulate(&model.model, query.as_deref(), &runs, format),
+        Command::Check {
+            model,
+            query,
+            confidence,
+            error,
+            bound,
+            threshold,
+            indifference,
+            alpha,
+            beta,
+            limits,
+            seed,
+            threads,
+            format,
+        } => {
+            let tapn = limits.apply(Tapn::load(&model.model)?);
+            let query = Query::parse(&query, &tapn.places)?;
+            limits.require_end("check", Some(&query))?;
+
+            let output = if let Some(threshold) = threshold {
+                let mut test = HypothesisTest::new(threshold, indifference, alpha, beta)?;
+                if let Some(seed) = seed {
+                    test = test.with_seed(seed);
+                }
+                if let Some(threads) = threads {
+                    test = test.with_threads(threads);
+                }
+                let verdict = test.decide(&tapn, &query)?;
+                let decision = match verdict.decision {
+                    Decision::AtLeast => "at_least",
+                    Decision::Below => "below",
+                    Decision::Inconclusive => "inconclusive",
+                };
+                vec![
+                    ("decision", json!(decision)),
+                    ("threshold", json!(threshold)),
+                    ("runs", json!(verdict.runs)),
+                    ("successes", json!(verdict.successes)),
+                    ("inconclusive", json!(verdict.inconclusive)),
+                    ("seed", json!(verdict.base_seed)),
+                ]
+            } else {
+                let bound = match bound {
+                    BoundArg::ChernoffHoeffding => Bound::ChernoffHoeffding,
+                    BoundArg::ClopperPearson => Bound::ClopperPearson,
+                };
+                let mut estimator = Estimator::new(confidence, error)?.with_bound(bound);
+                if let Some(seed) = seed {
+                    estimator = estimator.with_seed(seed);
+                }
+                if let Some(threads) = threads {
+                    estimator = estimator.with_threads(threads);
+                }
+                let estimate = estimator.estimate(&tapn, &query)?;
+                vec![
+                    ("probability", json!(estimate.probability)),
+                    ("lower", json!(estimate.interval.0)),
+                    ("upper", json!(estimate.interval.1)),
+                    ("confidence", json!(estimate.confidence)),
+                    ("runs", json!(estimate.runs)),
+                    ("successes", json!(estimate.successes)),
+                    ("inconclusive", json!(estimate.inconclusive)),
+                    ("seed", json!(estimate.base_seed)),
+                ]
+            };
+
+            print_table(&output, format);
+            Ok(())
+        }
+        Command::Export {
+            model,
+            to,
+            output,
+            highlight_enabled,
+        } => {
+            let tapn = Tapn::load(&model.model)?;
+            let text = match to {
+                ExportFormat::Dot => tapn
+                    .to_dot_with(&DotOptions::default().with_highlight_enabled(highlight_enabled)),
+                ExportFormat::Pnml => tapn.to_tapaal()?,
+                ExportFormat::Json => tapn.to_json(),
+            };
+
+            match output {
+                Some(path) => fs::write(path, text)?,
+                None => print!("{text}"),
+            }
+            Ok(())
+        }
+        Command::Validate { model } => {
+            let tapn = Tapn::load(&model.model)?;
+            println!(
+                "{} is valid: {} places, {} transitions, {} tokens",
+                model.model.display(),
+                tapn.places.len(),
+                tapn.transitions.len(),
+                tapn.marking.tokens.iter().map(Vec::len).sum::<usize>()
+            );
+            Ok(())
+        }
+    }
+}
+
+impl LimitArgs {
+    /// Fails unless every run ends, because of a time-bounded query or a limit.
+    /// Runs on a live net would go on forever otherwise.
+    fn require_end(&self, command: &str, query: Option<&Query>) -> Result<(), Box<dyn Error>> {
+        let bounded = query.is_some_and(|query| query.quantifier.time_bound().is_some());
+        if bounded || self.time_limit.is_some() || self.step_limit.is_some() {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{command}` needs a `PF<=t` or `PG<=t` query, or a `--time-limit` or `--step-limit`"
+            )
+            .into())
+        }
+    }
+
+    fn apply(&self, mut tapn: Tapn) -> Tapn {
+        tapn.time_limit = self.time_limit;
+        tapn.step_limit = self.step_limit;
+        tapn
+    }
+}
+
+/// Outcome of a single simulated run
+struct Run {
+    outcome: RunOutcome,
+    steps: usize,
+    end_time: f64,
+    firings: Vec<usize>,
+    /// Answer of the query, `None` without a query or if a limit cut the run off first
+    satisfied: Option<bool>,
+}
+
+fn simulate(
+    path: &Path,
+    query: Option<&str>,
+    args: &RunArgs,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let mut tapn = args.limits.apply(Tapn::load(path)?);
+    let query = query
+        .map(|query| Query::parse(query, &tapn.places))
+        .transpose()?;
+    args.limits.require_end("simulate", query.as_ref())?;
+    if let Some(query) = &query {
+        let observer: Box<dyn SimulationObserver> = Box::new(query.observer(&tapn));
+        tapn.add_observer(observer);
+    }
+
+    let mut batch = Batch::new(args.runs, args.seed.unwrap_or_else(rand::random));
+    if let Some(threads) = args.threads {
+        batch = batch.with_threads(threads);
+    }
+
+    let runs = batch.map(0..args.runs, |_, seed| {
+        let mut tapn = tapn.clone();
+        tapn.reset();
+        tapn.reseed(seed);
+        let result = tapn.run()?;
+
+        // The observer has stopped the run once the answer is known, runs that ended before
+        // that stay unanswered if a limit cut them off
+        let satisfied = query.as_ref().and_then(|query| {
+            query
+                .decide(&tapn.marking, tapn.current_time)
+                .or_else(|| query.conclude(&result.outcome, tapn.current_time))
+        });
+
+        Ok(Run {
+            outcome: result.outcome,
+            steps: result.steps,
+            end_time: result.end_time,
+            firings: result.firings,
+            satisfied,
+        })
+    });
+    let runs = runs.into_iter().collect::<Result<Vec<_>, TapnError>>()?;
+
+    let steps: Vec<f64> = runs.iter().map(|run| run.steps as f64).collect();
+    let times: Vec<f64> = runs.iter().map(|run| run.end_time).collect();
+
+    let mut output = vec![
+        ("runs", json!(runs.len())),
+        ("seed", json!(batch.base_seed)),
+    ];
+    for (name, values) in [("steps", &steps), ("time", &times)] {
+        let (mean, std_dev, min, max) = summarize(values);
+        output.push((
+            name,
+            json!({"mean": mean, "std_dev": std_dev, "min": min, "max": max}),
+        ));
+    }
+
+    let mut outcomes = serde_json::Map::new();
+    for (name, kind) in [
+        ("stopped", None),
+        ("deadlock", Some(RunOutcome::Deadlock)),
+        ("time_lock", Some(RunOutcome::TimeLock)),
+        ("time_limit", Some(RunOutcome::TimeLimit)),
+        ("step_limit", Some(RunOutcome::StepLimit)),
+    ] {
+        let count = runs
+            .iter()
+            .filter(|run| match kind {
+                None => matches!(run.outcome, RunOutcome::Stopped { .. }),
+                Some(kind) => run.outcome == kind,
+            })
+            .count();
+        outcomes.insert(name.to_string(), json!(count));
+    }
+    output.push(("outcomes", outcomes.into()));
+
+    if query.is_some() {
+        for (name, answer) in [
+            ("satisfied", Some(true)),
+            ("violated", Some(false)),
+            ("inconclusive", None),
+        ] {
+            let count = runs.iter().filter(|run| run.satisfied == answer).count();
+            output.push((name, json!(count as f64 / runs.len() as f64)));
+        }
+    }
+
+    let firings: serde_json::Map<_, _> = (0..tapn.transitions.len())
+        .map(|transition| {
+            let total: usize = runs.iter().map(|run| run.firings[transition]).sum();
+            (
+                format!("T{transition}"),
+                json!(total as f64 / runs.len() as f64),
+            )
+        })
+        .collect();
+    output.push(("mean_firings", firings.into()));
+
+    print_table(&output, format);
+    Ok(())
+}
+
+/// Mean, standard deviation, minimum and maximum
+fn summarize(values: &[f64]) -> (f64, f64, f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    (mean, variance.sqrt(), min, max)
+}
+
+/// Prints named values, flattening nested objects into `outer.inner` names for text and CSV
+fn print_table(values: &[(&str, serde_json::Value)], format: Format) {
+    if format == Format::Json {
+        let object: serde_json::Map<_, _> = values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&object).unwrap());
+        return;
     }
 
-    // Calculate averages
-    let avg_steps = total_steps as f64 / num_simulations as f64;
-    let avg_firings = total_firings as f64 / num_simulations as f64;
+    let mut flat = Vec::new();
+    for (name, value) in values {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (field, value) in fields {
+                    flat.push((format!("{name}.{field}"), value.to_string()));
+                }
+            }
+            value => flat.push((name.to_string(), value.to_string())),
+        }
+    }
 
-    // Print results
-    println!("Ran {} simulations", num_simulations);
-    println!("Average steps to completion: {:?}", avg_steps);
-    println!("Average delay transition firings: {:?}", avg_firings);
-}
\ No newline at end of file
+    if format == Format::Csv {
+        let (names, values): (Vec<_>, Vec<_>) = flat.into_iter().unzip();
+        println!("{}", names.join(","));
+        println!("{}", values.join(","));
+    } else {
+        let width = flat.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, value) in flat {
+            println!("{name:width$}  {}", value.trim_matches('"'));
+        }
+    }
+}
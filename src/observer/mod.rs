@@ -1,7 +1,10 @@
+#[allow(clippy::module_inception)]
 pub mod observer;
+pub mod query_observer;
 pub mod token_observers;
 
 pub use observer::SimulationEvent;
-pub use observer::SimulationObserver;
+pub use observer::{ObserverClone, SimulationObserver};
 
+pub use query_observer::QueryObserver;
 pub use token_observers::{Comparison, TokenAgeObserver, TokenCoutObserver};
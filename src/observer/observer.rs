@@ -1,10 +1,34 @@
-pub trait SimulationObserver {
+use crate::RunOutcome;
+
+pub trait SimulationObserver: ObserverClone + Send + Sync {
     fn on_step(&mut self, event: &SimulationEvent);
-    fn on_completion(&mut self);
+    /// Called once `Tapn::run` has stopped, with the reason it stopped
+    fn on_completion(&mut self, outcome: &RunOutcome);
     fn should_stop(&self) -> bool;
+    /// Forgets everything observed so far, called when the net is reset
+    fn reset(&mut self);
+}
+
+/// Lets boxed observers be cloned together with the net they watch.
+/// Implemented for every observer that is `Clone`.
+pub trait ObserverClone {
+    fn clone_box(&self) -> Box<dyn SimulationObserver>;
+}
+
+impl<T: SimulationObserver + Clone + 'static> ObserverClone for T {
+    fn clone_box(&self) -> Box<dyn SimulationObserver> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn SimulationObserver> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
 
 pub enum SimulationEvent {
+    /// `firing_time` is the global time at which the transition fires
     TransitionFiring {
         transition_id: usize,
         firing_time: f64,
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use super::{SimulationEvent, SimulationObserver};
+use crate::{Marking, Query, RunOutcome, Tapn};
+
+/// Stops the simulation as soon as the query is answered for the current run
+#[derive(Clone)]
+pub struct QueryObserver {
+    pub query: Query,
+    /// Answer for the current run, `None` until it is known. Stays `None` if a limit ends
+    /// the run before that.
+    pub result: Option<bool>,
+    /// Time of the last event of the current run
+    time: f64,
+    /// Index of every place by its ID, events name places by ID
+    place_indices: HashMap<usize, usize>,
+    initial_marking: Marking,
+    marking: Marking,
+}
+
+impl QueryObserver {
+    /// Observer answering `query` for runs of `tapn` starting from its initial marking
+    pub fn new(query: Query, tapn: &Tapn) -> Self {
+        let place_indices = tapn
+            .places
+            .iter()
+            .enumerate()
+            .map(|(index, place)| (place.id, index))
+            .collect();
+
+        let mut observer = Self {
+            query,
+            result: None,
+            time: 0.0,
+            place_indices,
+            initial_marking: tapn.initial_marking.clone(),
+            marking: tapn.initial_marking.clone(),
+        };
+        observer.reset();
+        observer
+    }
+
+    fn decide(&mut self, time: f64) {
+        self.time = time;
+        if self.result.is_none() {
+            self.result = self.query.decide(&self.marking, time);
+        }
+    }
+}
+
+impl SimulationObserver for QueryObserver {
+    fn on_step(&mut self, event: &SimulationEvent) {
+        match event {
+            SimulationEvent::TokensChanged {
+                place_id,
+                new_tokens,
+            } => {
+                if let Some(&index) = self.place_indices.get(place_id) {
+                    self.marking.tokens[index].clone_from(new_tokens);
+                }
+            }
+            // All places have been updated by the time the firing is reported
+            SimulationEvent::TransitionFired { firing_time, .. } => self.decide(*firing_time),
+            SimulationEvent::TimeAdvanced { new_time, .. } => self.decide(*new_time),
+            SimulationEvent::TransitionFiring { .. } => {}
+        }
+    }
+
+    fn on_completion(&mut self, outcome: &RunOutcome) {
+        if self.result.is_none() {
+            self.result = self.query.conclude(outcome, self.time);
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.result.is_some()
+    }
+
+    fn reset(&mut self) {
+        self.marking = self.initial_marking.clone();
+        self.result = None;
+        self.decide(0.0);
+    }
+}
+
+impl Query {
+    /// Compiles the query into an observer that stops `Tapn::run` once it is answered
+    pub fn observer(&self, tapn: &Tapn) -> QueryObserver {
+        QueryObserver::new(self.clone(), tapn)
+    }
+}
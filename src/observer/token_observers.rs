@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
 use super::{SimulationEvent, SimulationObserver};
+use crate::RunOutcome;
 
+#[derive(Clone)]
 pub struct TokenAgeObserver {
     pub age_distribution: HashMap<usize, Vec<f64>>,
     pub max_allowed_age: f64,
@@ -39,28 +41,42 @@ impl SimulationObserver for TokenAgeObserver {
         }
     }
 
-    fn on_completion(&mut self) {
+    fn on_completion(&mut self, _outcome: &RunOutcome) {
         
     }
 
     fn should_stop(&self) -> bool {
         self.should_stop
     }
+
+    fn reset(&mut self) {
+        self.age_distribution.clear();
+        self.should_stop = false;
+    }
 }
 
+#[derive(Clone)]
 pub struct TokenCoutObserver {
     pub place_thesholds: HashMap<usize, (usize, Comparison)>,
     pub should_stop: bool,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Comparison {
     LessThan,
     LessOrEqual,
     Equal,
+    NotEqual,
     GreaterOrEqual,
     GreaterThan,
 }
 
+impl Default for TokenCoutObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TokenCoutObserver {
     pub fn new() -> Self {
         Self {
@@ -76,10 +92,18 @@ impl TokenCoutObserver {
     }
 
     fn check_condition(&self, count: usize, threshold: usize, comparison: &Comparison) -> bool {
-        match comparison {
+        comparison.holds(count, threshold)
+    }
+}
+
+impl Comparison {
+    /// Checks if `count` compares to `threshold` this way
+    pub fn holds(self, count: usize, threshold: usize) -> bool {
+        match self {
             Comparison::LessThan => count < threshold,
             Comparison::LessOrEqual => count <= threshold,
             Comparison::Equal => count == threshold,
+            Comparison::NotEqual => count != threshold,
             Comparison::GreaterOrEqual => count >= threshold,
             Comparison::GreaterThan => count > threshold,
         }
@@ -92,22 +116,25 @@ impl SimulationObserver for TokenCoutObserver {
             place_id,
             new_tokens,
         } = event
+            && let Some((threshold, comparison)) = self.place_thesholds.get(place_id)
         {
-            if let Some((threshold, comparison)) = self.place_thesholds.get(place_id) {
-                let token_count = new_tokens.len();
-                //println!("token_count {}", token_count);
-                if self.check_condition(token_count, *threshold, comparison) {
-                    self.should_stop = true
-                }
+            let token_count = new_tokens.len();
+            //println!("token_count {}", token_count);
+            if self.check_condition(token_count, *threshold, comparison) {
+                self.should_stop = true
             }
         }
     }
 
-    fn on_completion(&mut self) {
+    fn on_completion(&mut self, _outcome: &RunOutcome) {
         
     }
 
     fn should_stop(&self) -> bool {
         self.should_stop
     }
+
+    fn reset(&mut self) {
+        self.should_stop = false;
+    }
 }
@@ -1,114 +1,122 @@
-use std::{cell::RefCell, rc::Rc};
+use serde::{Deserialize, Serialize};
 
-use super::Place;
+use super::Marking;
+use crate::io::json::interval;
 
+/// Arcs refer to places by their index in `Tapn::places`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ArcType {
     Input(InputArc),
     Transport(TransportArc),
     Inhibitor(InhibitorArc),
 }
 
+/// Decides which of the tokens satisfying an arc's timing guard are consumed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenSelection {
+    /// The eligible tokens with the highest ages
+    #[default]
+    OldestEligible,
+    /// The eligible tokens with the lowest ages
+    YoungestEligible,
+    /// Eligible tokens picked uniformly at random
+    RandomEligible,
+    /// The eligible tokens that were added to the place first
+    Fifo,
+}
+
 impl ArcType {
-    pub fn fire(&mut self) -> Vec<f64> {
+    /// Index of the place the arc reads from
+    pub fn place(&self) -> usize {
         match self {
-            ArcType::Input(arc) => arc.fire(),
-            ArcType::Transport(arc) => arc.fire(),
-            ArcType::Inhibitor(arc) => arc.fire(),
+            ArcType::Input(arc) => arc.input,
+            ArcType::Transport(arc) => arc.input,
+            ArcType::Inhibitor(arc) => arc.input,
         }
     }
 
-    pub fn can_fire(&self) -> bool {
+    pub fn can_fire(&self, marking: &Marking) -> bool {
         match self {
-            ArcType::Input(arc) => arc.can_fire(),
-            ArcType::Transport(arc) => arc.can_fire(),
-            ArcType::Inhibitor(arc) => arc.can_fire(),
+            ArcType::Input(arc) => arc.can_fire(marking),
+            ArcType::Transport(arc) => arc.can_fire(marking),
+            ArcType::Inhibitor(arc) => arc.can_fire(marking),
         }
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InputArc {
-    pub input: Rc<RefCell<Place>>,
+    pub input: usize,
     pub weight: usize,
+    #[serde(with = "interval")]
     pub timing: [f64; 2],
+    #[serde(default)]
+    pub selection: TokenSelection,
 }
 
 impl InputArc {
-    pub fn fire(&mut self) -> Vec<f64> {
-        if self.can_fire() {
-            let removed = self.input.borrow_mut().remove_tokens(self.weight);
-            //println!("Input arc fired, removed tokens: {:?}", removed);
-            // Input arcs create new 0-age tokens
-            vec![0.0; self.weight]
-        } else {
-            vec![]
-        }
-    }
-
-    pub fn can_fire(&self) -> bool {
-        self.input.borrow().tokens_hold(self.weight, &self.timing) &&
-        self.input.borrow().invariants_hold(self.weight)
+    pub fn can_fire(&self, marking: &Marking) -> bool {
+        marking.tokens_hold(self.input, self.weight, &self.timing)
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransportArc {
-    pub input: Rc<RefCell<Place>>,
+    pub input: usize,
     pub weight: usize,
+    #[serde(with = "interval")]
     pub timing: [f64; 2],
+    #[serde(default)]
+    pub selection: TokenSelection,
+    /// Identifies the `TransportOutputArc` that receives the tokens with their ages
+    pub pair: usize,
 }
 
 impl TransportArc {
-    pub fn fire(&mut self) -> Vec<f64> {
-        if self.can_fire() {
-            let tokens = self.input.borrow_mut().remove_tokens(self.weight);
-            //println!("Transport arc fired, transporting tokens: {:?}", tokens);
-            tokens
-        } else {
-            vec![]
-        }
-    }
-
-    pub fn can_fire(&self) -> bool {
-        self.input.borrow().tokens_hold(self.weight, &self.timing) &&
-        self.input.borrow().invariants_hold(self.weight)
+    pub fn can_fire(&self, marking: &Marking) -> bool {
+        marking.tokens_hold(self.input, self.weight, &self.timing)
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InhibitorArc {
-    pub input: Rc<RefCell<Place>>,
+    pub input: usize,
     pub weight: usize,
     pub constraint: usize,
+    #[serde(with = "interval")]
     pub timing: [f64; 2],
 }
 
 impl InhibitorArc {
-    pub fn fire(&mut self) -> Vec<f64> {
-        // Inhibitor arcs don't actually consume tokens when firing
-        println!("Inhibitor arc fired (no tokens consumed)");
-        vec![]
-    }
-
-    pub fn can_fire(&self) -> bool {
+    pub fn can_fire(&self, marking: &Marking) -> bool {
         // Inhibitor checks if place has LESS tokens than constraint
-        self.input.borrow().tokens.len() < self.constraint &&
-        self.input.borrow().invariants_hold(0) // Check invariants for existing tokens
+        marking.count(self.input) < self.constraint
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum OutputArc {
+    #[serde(rename = "transport")]
     TransportArc(TransportOutputArc),
     Regular(RegularOutputArc),
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransportOutputArc {
-    pub output: Rc<RefCell<Place>>,
+    pub output: usize,
     pub weight: usize,
+    /// Identifies the `TransportArc` the tokens come from
+    pub pair: usize,
 }
 
 impl TransportOutputArc {
-    pub fn fire(&mut self, tokens: &[f64]) -> bool {
+    pub fn fire(&self, marking: &mut Marking, tokens: &[f64]) -> bool {
         if tokens.len() >= self.weight {
             // Transport arcs preserve the original token ages
-            self.output.borrow_mut().add_tokens(&tokens[..self.weight]);
+            marking.add_tokens(self.output, &tokens[..self.weight]);
             true
         } else {
             false
@@ -116,16 +124,17 @@ impl TransportOutputArc {
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RegularOutputArc {
-    pub output: Rc<RefCell<Place>>,
+    pub output: usize,
     pub weight: usize,
 }
 
 impl RegularOutputArc {
-    pub fn fire(&mut self, tokens: &[f64]) -> bool {
+    pub fn fire(&self, marking: &mut Marking, _tokens: &[f64]) -> bool {
         // Regular arcs create new 0-age tokens regardless of input
         // (but we use the weight from the arc definition)
-        self.output.borrow_mut().add_tokens(&vec![0.0; self.weight]);
+        marking.add_tokens(self.output, &vec![0.0; self.weight]);
         true
     }
 }
@@ -0,0 +1,460 @@
+use std::fmt;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use super::arcs::{ArcType, OutputArc, TokenSelection, TransportOutputArc};
+use super::invariant::AGE_EPSILON;
+use super::place::in_interval;
+use super::{Invariant, Marking, Place};
+
+/// Tokens picked for every input arc of a transition, computed before anything is removed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Binding {
+    /// Indices into the marking of each arc's place, indexed like the transition's input arcs.
+    /// Inhibitor arcs never get any tokens.
+    pub indices: Vec<Vec<usize>>,
+}
+
+/// Reason a transition could not be fired
+#[derive(Clone, Debug, PartialEq)]
+pub enum FiringError {
+    /// The input arc at index `arc` cannot get enough tokens within its guard
+    /// while the other arcs of the transition get theirs
+    NotEnoughTokens { transition: usize, arc: usize },
+    /// The inhibitor arc at index `arc` sees too many tokens
+    Inhibited { transition: usize, arc: usize },
+    /// The transport arcs with this pairing ID don't form exactly one input and output pair
+    /// of equal weight
+    UnpairedTransport { transition: usize, pair: usize },
+}
+
+impl fmt::Display for FiringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FiringError::NotEnoughTokens { transition, arc } => write!(
+                f,
+                "input arc {arc} of transition {transition} has too few tokens within its guard"
+            ),
+            FiringError::Inhibited { transition, arc } => {
+                write!(f, "transition {transition} is inhibited by arc {arc}")
+            }
+            FiringError::UnpairedTransport { transition, pair } => write!(
+                f,
+                "transport arcs of pair {pair} in transition {transition} don't match up"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FiringError {}
+
+/// An input arc that consumes tokens
+struct Demand {
+    arc: usize,
+    place: usize,
+    weight: usize,
+    guard: Guard,
+    selection: TokenSelection,
+}
+
+/// Ages a consumed token may have
+#[derive(Clone, Copy)]
+struct Guard {
+    /// Guard of the arc, with the upper bound narrowed to `invariant`
+    timing: [f64; 2],
+    /// Tightest invariant of the place a transport arc moves the token to
+    invariant: Option<Invariant>,
+}
+
+impl Guard {
+    fn admits(&self, age: f64) -> bool {
+        in_interval(age, &self.timing) && self.invariant.is_none_or(|inv| inv.check(age))
+    }
+
+    /// Checks if the token is, and stays, too old for the guard
+    fn expired(&self, age: f64) -> bool {
+        age >= self.timing[0] - AGE_EPSILON && !self.admits(age)
+    }
+}
+
+fn demands(arcs: &[ArcType], outputs: &[OutputArc], places: &[Place]) -> Vec<Demand> {
+    arcs.iter()
+        .enumerate()
+        .filter_map(|(arc, a)| match a {
+            ArcType::Input(a) => Some(Demand {
+                arc,
+                place: a.input,
+                weight: a.weight,
+                guard: Guard {
+                    timing: a.timing,
+                    invariant: None,
+                },
+                selection: a.selection,
+            }),
+            ArcType::Transport(a) => {
+                // Moved tokens keep their age, so they have to satisfy the target's invariants.
+                // Of two invariants with the same bound the strict one is tighter.
+                let invariant = transport_outputs(outputs, a.pair)
+                    .flat_map(|output| &places[output.output].invariants)
+                    .copied()
+                    .min_by(|a, b| a.bound.total_cmp(&b.bound).then(b.strict.cmp(&a.strict)));
+                let bound = invariant.map_or(f64::INFINITY, |inv| inv.bound);
+
+                Some(Demand {
+                    arc,
+                    place: a.input,
+                    weight: a.weight,
+                    guard: Guard {
+                        timing: [a.timing[0], a.timing[1].min(bound)],
+                        invariant,
+                    },
+                    selection: a.selection,
+                })
+            }
+            ArcType::Inhibitor(_) => None,
+        })
+        .collect()
+}
+
+fn transport_outputs(
+    outputs: &[OutputArc],
+    pair: usize,
+) -> impl Iterator<Item = &TransportOutputArc> {
+    outputs.iter().filter_map(move |arc| match arc {
+        OutputArc::TransportArc(arc) if arc.pair == pair => Some(arc),
+        _ => None,
+    })
+}
+
+/// Finds a pairing ID that doesn't connect exactly one input and one output arc of equal weight
+pub(super) fn unpaired(arcs: &[ArcType], outputs: &[OutputArc]) -> Option<usize> {
+    let inputs: Vec<_> = arcs
+        .iter()
+        .filter_map(|arc| match arc {
+            ArcType::Transport(arc) => Some((arc.pair, arc.weight)),
+            _ => None,
+        })
+        .collect();
+    let outputs: Vec<_> = outputs
+        .iter()
+        .filter_map(|arc| match arc {
+            OutputArc::TransportArc(arc) => Some((arc.pair, arc.weight)),
+            _ => None,
+        })
+        .collect();
+
+    inputs
+        .iter()
+        .chain(&outputs)
+        .map(|&(pair, _)| pair)
+        .find(|&pair| {
+            let input: Vec<_> = inputs.iter().filter(|(p, _)| *p == pair).collect();
+            let output: Vec<_> = outputs.iter().filter(|(p, _)| *p == pair).collect();
+            input.len() != 1 || output.len() != 1 || input[0].1 != output[0].1
+        })
+}
+
+/// Earliest positive delay at which a token enters or leaves the guard of a consuming arc
+pub fn next_change(
+    arcs: &[ArcType],
+    outputs: &[OutputArc],
+    marking: &Marking,
+    places: &[Place],
+) -> f64 {
+    demands(arcs, outputs, places)
+        .iter()
+        .map(|demand| marking.next_interval_change(demand.place, &demand.guard.timing))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn inhibited(arcs: &[ArcType], marking: &Marking) -> Option<usize> {
+    arcs.iter().position(|arc| match arc {
+        ArcType::Inhibitor(arc) => !arc.can_fire(marking),
+        _ => false,
+    })
+}
+
+/// Checks if a binding exists for the arcs once `delay` time units have passed
+pub fn can_bind(
+    arcs: &[ArcType],
+    outputs: &[OutputArc],
+    marking: &Marking,
+    places: &[Place],
+    delay: f64,
+) -> bool {
+    if inhibited(arcs, marking).is_some() || unpaired(arcs, outputs).is_some() {
+        return false;
+    }
+
+    // Arcs only compete for tokens with arcs reading the same place
+    let demands = demands(arcs, outputs, places);
+    demands.iter().enumerate().all(|(i, demand)| {
+        let first_on_place = !demands[..i]
+            .iter()
+            .any(|d| d.place == demand.place);
+        if !first_on_place {
+            return true;
+        }
+
+        let ages: Vec<f64> = marking
+            .tokens(demand.place)
+            .iter()
+            .map(|age| age + delay)
+            .collect();
+        let guards: Vec<_> = demands[i..]
+            .iter()
+            .filter(|d| d.place == demand.place)
+            .map(|d| (d.guard, d.weight))
+            .collect();
+        feasible(ages, guards)
+    })
+}
+
+/// Picks the tokens every arc consumes, following each arc's selection policy as long as
+/// the arcs after it can still be satisfied. Fails without touching the places.
+pub fn bind<R: Rng + ?Sized>(
+    arcs: &[ArcType],
+    outputs: &[OutputArc],
+    marking: &Marking,
+    places: &[Place],
+    transition: usize,
+    rng: &mut R,
+) -> Result<Binding, FiringError> {
+    if let Some(pair) = unpaired(arcs, outputs) {
+        return Err(FiringError::UnpairedTransport { transition, pair });
+    }
+    if let Some(arc) = inhibited(arcs, marking) {
+        return Err(FiringError::Inhibited { transition, arc });
+    }
+
+    let demands = demands(arcs, outputs, places);
+    let mut binding = Binding {
+        indices: vec![Vec::new(); arcs.len()],
+    };
+
+    for (i, demand) in demands.iter().enumerate() {
+        let tokens = marking.tokens(demand.place);
+
+        // Tokens of this place already bound to earlier arcs
+        let mut taken = vec![false; tokens.len()];
+        for earlier in demands[..i]
+            .iter()
+            .filter(|d| d.place == demand.place)
+        {
+            for &index in &binding.indices[earlier.arc] {
+                taken[index] = true;
+            }
+        }
+
+        let later: Vec<_> = demands[i + 1..]
+            .iter()
+            .filter(|d| d.place == demand.place)
+            .map(|d| (d.guard, d.weight))
+            .collect();
+
+        let mut candidates: Vec<usize> = (0..tokens.len())
+            .filter(|&index| !taken[index] && demand.guard.admits(tokens[index]))
+            .collect();
+        match demand.selection {
+            TokenSelection::Fifo => {}
+            TokenSelection::OldestEligible => {
+                candidates.sort_by(|&a, &b| tokens[b].total_cmp(&tokens[a]))
+            }
+            TokenSelection::YoungestEligible => {
+                candidates.sort_by(|&a, &b| tokens[a].total_cmp(&tokens[b]))
+            }
+            TokenSelection::RandomEligible => candidates.shuffle(rng),
+        }
+
+        let mut chosen = Vec::with_capacity(demand.weight);
+        for index in candidates {
+            if chosen.len() == demand.weight {
+                break;
+            }
+
+            // Only take the token if the rest of this arc and the later arcs can still be served
+            taken[index] = true;
+            let ages = (0..tokens.len())
+                .filter(|&j| !taken[j])
+                .map(|j| tokens[j])
+                .collect();
+            let mut guards = later.clone();
+            guards.push((demand.guard, demand.weight - chosen.len() - 1));
+
+            if feasible(ages, guards) {
+                chosen.push(index);
+            } else {
+                taken[index] = false;
+            }
+        }
+
+        if chosen.len() < demand.weight {
+            return Err(FiringError::NotEnoughTokens {
+                transition,
+                arc: demand.arc,
+            });
+        }
+        binding.indices[demand.arc] = chosen;
+    }
+
+    Ok(binding)
+}
+
+/// Checks if every guard can get its number of distinct tokens from `ages`.
+/// Tokens are handed out from youngest to oldest to the open guard that closes first,
+/// which finds an assignment whenever one exists.
+fn feasible(mut ages: Vec<f64>, mut guards: Vec<(Guard, usize)>) -> bool {
+    ages.sort_by(f64::total_cmp);
+
+    for age in ages {
+        // Later tokens are even older, so a guard that closed with tokens missing stays unserved
+        if guards
+            .iter()
+            .any(|(guard, count)| *count > 0 && guard.expired(age))
+        {
+            return false;
+        }
+
+        let closing_first = guards
+            .iter_mut()
+            .filter(|(guard, count)| *count > 0 && guard.admits(age))
+            .min_by(|(a, _), (b, _)| a.timing[1].total_cmp(&b.timing[1]));
+        if let Some((_, count)) = closing_first {
+            *count -= 1;
+        }
+    }
+
+    guards.iter().all(|(_, count)| *count == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::petri::rng;
+    use crate::petri::testing::places;
+    use crate::{Distribution, InputArc, Transition, TransportArc};
+
+    fn input(place: usize, timing: [f64; 2], weight: usize, selection: TokenSelection) -> ArcType {
+        ArcType::Input(InputArc {
+            input: place,
+            weight,
+            timing,
+            selection,
+        })
+    }
+
+    fn bind_arcs(arcs: &[ArcType], marking: &Marking, seed: u64) -> Result<Binding, FiringError> {
+        let places = places(marking.tokens.len());
+        bind(arcs, &[], marking, &places, 0, &mut rng::stream(seed, 0))
+    }
+
+    fn guard(timing: [f64; 2]) -> Guard {
+        Guard {
+            timing,
+            invariant: None,
+        }
+    }
+
+    #[test]
+    fn feasible_needs_distinct_tokens_within_every_guard() {
+        let wide = guard([0.0, 5.0]);
+        let narrow = guard([0.0, 2.0]);
+        assert!(feasible(vec![1.0, 3.0], vec![(wide, 1), (narrow, 1)]));
+        assert!(!feasible(vec![1.0, 3.0], vec![(narrow, 2)]));
+        assert!(!feasible(vec![1.0], vec![(wide, 1), (wide, 1)]));
+    }
+
+    #[test]
+    fn overlapping_guards_on_one_place_share_its_tokens() {
+        // The young token is the only one the narrow guard accepts, so the wide guard
+        // has to pass it over even though it prefers young tokens
+        let marking = Marking::new(vec![vec![1.0, 3.0]]);
+        let arcs = [
+            input(0, [0.0, 5.0], 1, TokenSelection::YoungestEligible),
+            input(0, [0.0, 2.0], 1, TokenSelection::YoungestEligible),
+        ];
+        let binding = bind_arcs(&arcs, &marking, 1).unwrap();
+        assert_eq!(binding.indices, vec![vec![1], vec![0]]);
+    }
+
+    #[test]
+    fn failed_binding_leaves_the_marking_untouched() {
+        let mut marking = Marking::new(vec![vec![1.0], vec![0.5]]);
+        let before = marking.clone();
+        let mut transition = Transition::new(
+            vec![
+                input(1, [0.0, 5.0], 1, TokenSelection::default()),
+                input(0, [0.0, 5.0], 1, TokenSelection::default()),
+                input(0, [0.0, 5.0], 1, TokenSelection::default()),
+            ],
+            Vec::new(),
+            Distribution::Constant(0.0),
+            false,
+            4,
+        );
+
+        // Arc 1 can't take the only token without starving arc 2
+        let result = transition.fire(&mut marking, &places(2), &mut rng::stream(1, 0));
+        assert_eq!(
+            result,
+            Err(FiringError::NotEnoughTokens {
+                transition: 4,
+                arc: 1
+            })
+        );
+        assert_eq!(marking, before);
+    }
+
+    #[test]
+    fn transported_tokens_respect_strict_target_invariants() {
+        let marking = Marking::new(vec![vec![2.0], Vec::new()]);
+        let arcs = [ArcType::Transport(TransportArc {
+            input: 0,
+            weight: 1,
+            timing: [0.0, f64::INFINITY],
+            selection: TokenSelection::default(),
+            pair: 0,
+        })];
+        let outputs = [OutputArc::TransportArc(TransportOutputArc {
+            output: 1,
+            weight: 1,
+            pair: 0,
+        })];
+        let target = |invariant| {
+            vec![
+                Place::new(0, "P0".to_string(), Vec::new()),
+                Place::new(1, "P1".to_string(), vec![invariant]),
+            ]
+        };
+
+        let at_most = target(Invariant::at_most(2.0));
+        assert!(can_bind(&arcs, &outputs, &marking, &at_most, 0.0));
+        let less_than = target(Invariant::less_than(2.0));
+        assert!(!can_bind(&arcs, &outputs, &marking, &less_than, 0.0));
+        let mut rng = rng::stream(1, 0);
+        assert!(bind(&arcs, &outputs, &marking, &less_than, 0, &mut rng).is_err());
+    }
+
+    #[test]
+    fn selection_policies_pick_among_eligible_tokens() {
+        // Tokens in the order they were added, the last one is outside the guard
+        let marking = Marking::new(vec![vec![1.5, 2.4, 1.2, 4.0]]);
+        let picked = |selection| {
+            let arcs = [input(0, [1.0, 2.5], 1, selection)];
+            bind_arcs(&arcs, &marking, 1).unwrap().indices[0][0]
+        };
+
+        assert_eq!(picked(TokenSelection::OldestEligible), 1);
+        assert_eq!(picked(TokenSelection::YoungestEligible), 2);
+        assert_eq!(picked(TokenSelection::Fifo), 0);
+
+        let arcs = [input(0, [1.0, 2.5], 1, TokenSelection::RandomEligible)];
+        let random: HashSet<_> = (0..64)
+            .map(|seed| bind_arcs(&arcs, &marking, seed).unwrap().indices[0][0])
+            .collect();
+        assert_eq!(random, HashSet::from([0, 1, 2]));
+    }
+}
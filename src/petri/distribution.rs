@@ -0,0 +1,381 @@
+use std::fmt;
+
+use rand::Rng;
+use rand::distr::Uniform;
+use rand::distr::weighted::WeightedIndex;
+use rand_distr::{Exp, Exp1, Gamma, Geometric, LogNormal, StandardNormal, Triangular, Weibull};
+use serde::{Deserialize, Serialize};
+
+/// Distribution of the firing delay of a transition
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    Constant(f64),
+    /// Continuous uniform delay in `[min, max)`
+    Uniform(f64, f64),
+    /// Exponential delay with the given rate
+    Exponential(f64),
+    /// Normal delay with mean and standard deviation, truncated to non-negative values
+    Normal(f64, f64),
+    /// Delay whose logarithm is normal with location `mu` and scale `sigma`
+    LogNormal(f64, f64),
+    /// Gamma delay with shape and scale
+    Gamma(f64, f64),
+    /// Sum of `k` exponential delays with the given rate
+    Erlang(u32, f64),
+    /// Weibull delay with scale and shape
+    Weibull(f64, f64),
+    /// Triangular delay with min, mode, and max
+    Triangular(f64, f64, f64),
+    /// Number of failed trials before the first success with probability `p`
+    Geometric(f64),
+    /// Integer delay drawn uniformly from `min..=max`
+    DiscreteUniform(u64, u64),
+    /// One of `values`, picked in proportion to `weights`
+    Empirical { values: Vec<f64>, weights: Vec<f64> },
+}
+
+/// Reason a distribution was rejected when it was constructed
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistributionError {
+    /// A parameter is not finite or lies outside its allowed range
+    InvalidParameter {
+        distribution: &'static str,
+        parameter: &'static str,
+        value: f64,
+    },
+    /// The lower end of a range is not below its upper end
+    EmptyRange {
+        distribution: &'static str,
+        min: f64,
+        max: f64,
+    },
+    /// An empirical distribution without values, with mismatched weights, or only zero weights
+    InvalidWeights,
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributionError::InvalidParameter {
+                distribution,
+                parameter,
+                value,
+            } => write!(f, "invalid {parameter} {value} for {distribution} distribution"),
+            DistributionError::EmptyRange {
+                distribution,
+                min,
+                max,
+            } => write!(f, "empty range [{min}, {max}] for {distribution} distribution"),
+            DistributionError::InvalidWeights => {
+                write!(f, "empirical distribution needs values with matching, non-zero weights")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DistributionError {}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name();
+        match self {
+            Distribution::Constant(value) => write!(f, "{name}({value})"),
+            Distribution::Exponential(rate) => write!(f, "{name}({rate})"),
+            Distribution::Geometric(p) => write!(f, "{name}({p})"),
+            Distribution::Uniform(a, b)
+            | Distribution::Normal(a, b)
+            | Distribution::LogNormal(a, b)
+            | Distribution::Gamma(a, b)
+            | Distribution::Weibull(a, b) => write!(f, "{name}({a}, {b})"),
+            Distribution::Erlang(k, rate) => write!(f, "{name}({k}, {rate})"),
+            Distribution::Triangular(min, mode, max) => write!(f, "{name}({min}, {mode}, {max})"),
+            Distribution::DiscreteUniform(min, max) => write!(f, "{name}({min}, {max})"),
+            Distribution::Empirical { values, .. } => write!(f, "{name}({} values)", values.len()),
+        }
+    }
+}
+
+impl Distribution {
+    pub fn constant(value: f64) -> Result<Self, DistributionError> {
+        Self::Constant(value).validated()
+    }
+
+    pub fn uniform(min: f64, max: f64) -> Result<Self, DistributionError> {
+        Self::Uniform(min, max).validated()
+    }
+
+    pub fn exponential(rate: f64) -> Result<Self, DistributionError> {
+        Self::Exponential(rate).validated()
+    }
+
+    pub fn normal(mean: f64, std_dev: f64) -> Result<Self, DistributionError> {
+        Self::Normal(mean, std_dev).validated()
+    }
+
+    pub fn log_normal(mu: f64, sigma: f64) -> Result<Self, DistributionError> {
+        Self::LogNormal(mu, sigma).validated()
+    }
+
+    pub fn gamma(shape: f64, scale: f64) -> Result<Self, DistributionError> {
+        Self::Gamma(shape, scale).validated()
+    }
+
+    pub fn erlang(k: u32, rate: f64) -> Result<Self, DistributionError> {
+        Self::Erlang(k, rate).validated()
+    }
+
+    pub fn weibull(scale: f64, shape: f64) -> Result<Self, DistributionError> {
+        Self::Weibull(scale, shape).validated()
+    }
+
+    pub fn triangular(min: f64, mode: f64, max: f64) -> Result<Self, DistributionError> {
+        Self::Triangular(min, mode, max).validated()
+    }
+
+    pub fn geometric(p: f64) -> Result<Self, DistributionError> {
+        Self::Geometric(p).validated()
+    }
+
+    pub fn discrete_uniform(min: u64, max: u64) -> Result<Self, DistributionError> {
+        Self::DiscreteUniform(min, max).validated()
+    }
+
+    pub fn empirical(values: Vec<f64>, weights: Vec<f64>) -> Result<Self, DistributionError> {
+        Self::Empirical { values, weights }.validated()
+    }
+
+    fn validated(self) -> Result<Self, DistributionError> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Checks that all parameters are in range, so sampling only yields non-negative delays
+    pub fn validate(&self) -> Result<(), DistributionError> {
+        let name = self.name();
+        let non_negative = |parameter, value: f64| check(name, parameter, value, value >= 0.0);
+        let positive = |parameter, value: f64| check(name, parameter, value, value > 0.0);
+        let finite = |parameter, value: f64| check(name, parameter, value, true);
+
+        match self {
+            Distribution::Constant(value) => non_negative("value", *value),
+            Distribution::Uniform(min, max) => {
+                non_negative("min", *min)?;
+                finite("max", *max)?;
+                ordered(name, *min, *max)
+            }
+            Distribution::Exponential(rate) => positive("rate", *rate),
+            Distribution::Normal(mean, std_dev) => {
+                finite("mean", *mean)?;
+                positive("standard deviation", *std_dev)
+            }
+            Distribution::LogNormal(mu, sigma) => {
+                finite("mu", *mu)?;
+                positive("sigma", *sigma)
+            }
+            Distribution::Gamma(shape, scale) => {
+                positive("shape", *shape)?;
+                positive("scale", *scale)
+            }
+            Distribution::Erlang(k, rate) => {
+                positive("k", *k as f64)?;
+                positive("rate", *rate)
+            }
+            Distribution::Weibull(scale, shape) => {
+                positive("scale", *scale)?;
+                positive("shape", *shape)
+            }
+            Distribution::Triangular(min, mode, max) => {
+                non_negative("min", *min)?;
+                finite("max", *max)?;
+                ordered(name, *min, *max)?;
+                check(name, "mode", *mode, *min <= *mode && *mode <= *max)
+            }
+            Distribution::Geometric(p) => check(name, "p", *p, *p > 0.0 && *p <= 1.0),
+            Distribution::DiscreteUniform(min, max) => {
+                if min > max {
+                    return Err(DistributionError::EmptyRange {
+                        distribution: name,
+                        min: *min as f64,
+                        max: *max as f64,
+                    });
+                }
+                Ok(())
+            }
+            Distribution::Empirical { values, weights } => {
+                for &value in values {
+                    non_negative("value", value)?;
+                }
+                let weights_valid = weights.iter().all(|w| w.is_finite() && *w >= 0.0)
+                    && weights.iter().sum::<f64>() > 0.0;
+                if values.is_empty() || values.len() != weights.len() || !weights_valid {
+                    return Err(DistributionError::InvalidWeights);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Constant(_) => "constant",
+            Distribution::Uniform(_, _) => "uniform",
+            Distribution::Exponential(_) => "exponential",
+            Distribution::Normal(_, _) => "normal",
+            Distribution::LogNormal(_, _) => "log-normal",
+            Distribution::Gamma(_, _) => "gamma",
+            Distribution::Erlang(_, _) => "erlang",
+            Distribution::Weibull(_, _) => "weibull",
+            Distribution::Triangular(_, _, _) => "triangular",
+            Distribution::Geometric(_) => "geometric",
+            Distribution::DiscreteUniform(_, _) => "discrete uniform",
+            Distribution::Empirical { .. } => "empirical",
+        }
+    }
+
+    /// Draws a single delay, or fails if the parameters don't pass `validate`.
+    /// Drawing many delays is cheaper from a `sampler`, which validates only once.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<f64, DistributionError> {
+        Ok(self.sampler()?.sample(rng))
+    }
+
+    /// Validates the parameters and prepares everything needed to draw delays
+    pub fn sampler(&self) -> Result<Sampler, DistributionError> {
+        const VALIDATED: &str = "distribution parameters were validated above";
+
+        // The variants are public, so the parameters may not have gone through a constructor
+        self.validate()?;
+
+        let kind = match self {
+            Distribution::Constant(value) => SamplerKind::Constant(*value),
+            Distribution::Uniform(min, max) => {
+                SamplerKind::Uniform(Uniform::new(*min, *max).expect(VALIDATED))
+            }
+            Distribution::Exponential(rate) => {
+                SamplerKind::Exponential(Exp::new(*rate).expect(VALIDATED))
+            }
+            Distribution::Normal(mean, std_dev) => SamplerKind::Normal(*mean, *std_dev),
+            Distribution::LogNormal(mu, sigma) => {
+                SamplerKind::LogNormal(LogNormal::new(*mu, *sigma).expect(VALIDATED))
+            }
+            Distribution::Gamma(shape, scale) => {
+                SamplerKind::Gamma(Gamma::new(*shape, *scale).expect(VALIDATED))
+            }
+            Distribution::Erlang(k, rate) => {
+                SamplerKind::Gamma(Gamma::new(*k as f64, 1.0 / *rate).expect(VALIDATED))
+            }
+            Distribution::Weibull(scale, shape) => {
+                SamplerKind::Weibull(Weibull::new(*scale, *shape).expect(VALIDATED))
+            }
+            Distribution::Triangular(min, mode, max) => {
+                SamplerKind::Triangular(Triangular::new(*min, *max, *mode).expect(VALIDATED))
+            }
+            Distribution::Geometric(p) => {
+                SamplerKind::Geometric(Geometric::new(*p).expect(VALIDATED))
+            }
+            Distribution::DiscreteUniform(min, max) => SamplerKind::DiscreteUniform(*min, *max),
+            Distribution::Empirical { values, weights } => SamplerKind::Empirical(
+                values.clone(),
+                WeightedIndex::new(weights).expect(VALIDATED),
+            ),
+        };
+        Ok(Sampler { kind })
+    }
+}
+
+/// A distribution whose parameters have been validated, so drawing from it can't fail
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    kind: SamplerKind,
+}
+
+#[derive(Clone, Debug)]
+enum SamplerKind {
+    Constant(f64),
+    Uniform(Uniform<f64>),
+    Exponential(Exp<f64>),
+    /// Mean and standard deviation of the truncated normal
+    Normal(f64, f64),
+    LogNormal(LogNormal<f64>),
+    Gamma(Gamma<f64>),
+    Weibull(Weibull<f64>),
+    Triangular(Triangular<f64>),
+    Geometric(Geometric),
+    DiscreteUniform(u64, u64),
+    Empirical(Vec<f64>, WeightedIndex<f64>),
+}
+
+impl Sampler {
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match &self.kind {
+            SamplerKind::Constant(value) => *value,
+            SamplerKind::Uniform(range) => rng.sample(range),
+            SamplerKind::Exponential(exp) => rng.sample(exp),
+            SamplerKind::Normal(mean, std_dev) => sample_truncated_normal(*mean, *std_dev, rng),
+            SamplerKind::LogNormal(log_normal) => rng.sample(log_normal),
+            SamplerKind::Gamma(gamma) => rng.sample(gamma),
+            SamplerKind::Weibull(weibull) => rng.sample(weibull),
+            SamplerKind::Triangular(triangular) => rng.sample(triangular),
+            SamplerKind::Geometric(geometric) => rng.sample(geometric) as f64,
+            SamplerKind::DiscreteUniform(min, max) => rng.random_range(*min..=*max) as f64,
+            SamplerKind::Empirical(values, index) => values[rng.sample(index)],
+        }
+    }
+}
+
+fn check(
+    distribution: &'static str,
+    parameter: &'static str,
+    value: f64,
+    in_range: bool,
+) -> Result<(), DistributionError> {
+    if value.is_finite() && in_range {
+        Ok(())
+    } else {
+        Err(DistributionError::InvalidParameter {
+            distribution,
+            parameter,
+            value,
+        })
+    }
+}
+
+fn ordered(distribution: &'static str, min: f64, max: f64) -> Result<(), DistributionError> {
+    if min < max {
+        Ok(())
+    } else {
+        Err(DistributionError::EmptyRange {
+            distribution,
+            min,
+            max,
+        })
+    }
+}
+
+/// Samples a normal distribution conditioned on being non-negative
+fn sample_truncated_normal<R: Rng + ?Sized>(mean: f64, std_dev: f64, rng: &mut R) -> f64 {
+    // Truncation point in standard units
+    let lower = -mean / std_dev;
+
+    let z = if lower <= 0.0 {
+        // At least half of the mass is kept, so plain rejection is cheap
+        loop {
+            let z: f64 = rng.sample(StandardNormal);
+            if z >= lower {
+                break z;
+            }
+        }
+    } else {
+        // Far in the tail, use Robert's (1995) exponential proposal
+        let alpha = (lower + (lower * lower + 4.0).sqrt()) / 2.0;
+        loop {
+            let z = lower + rng.sample::<f64, _>(Exp1) / alpha;
+            let acceptance = (-(z - alpha).powi(2) / 2.0).exp();
+            if rng.random::<f64>() <= acceptance {
+                break z;
+            }
+        }
+    };
+
+    (mean + std_dev * z).max(0.0)
+}
@@ -1,16 +1,50 @@
-#[derive(Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+/// Tolerance for comparing token ages against bounds, absorbs rounding from repeated delays
+pub(crate) const AGE_EPSILON: f64 = 1e-9;
+
+/// Age invariant of a place: every token in the place must stay below `bound`.
+/// Time may not pass beyond the point where a token would violate it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Invariant {
-    condition: fn(token_age: f64) -> bool,
+    #[serde(with = "crate::io::json::bound")]
+    pub bound: f64,
+    /// `age < bound` when set, `age <= bound` otherwise
+    pub strict: bool,
 }
 
 impl Invariant {
-    pub fn new(condition: fn(token_age: f64) -> bool) -> Invariant {
+    /// Invariant `age <= bound`
+    pub fn at_most(bound: f64) -> Invariant {
+        Invariant {
+            bound,
+            strict: false,
+        }
+    }
+
+    /// Invariant `age < bound`
+    pub fn less_than(bound: f64) -> Invariant {
         Invariant {
-            condition
+            bound,
+            strict: true,
         }
     }
 
     pub fn check(&self, token_age: f64) -> bool {
-        (self.condition)(token_age)
+        if self.strict {
+            token_age < self.bound
+        } else {
+            token_age <= self.bound + AGE_EPSILON
+        }
+    }
+
+    pub fn upper_bound(&self) -> f64 {
+        self.bound
     }
-}
\ No newline at end of file
+
+    /// Longest delay a token of age `token_age` may take without violating the invariant.
+    /// For strict invariants this is the supremum, which is reached when forcing a firing.
+    pub fn max_delay(&self, token_age: f64) -> f64 {
+        (self.bound - token_age).max(0.0)
+    }
+}
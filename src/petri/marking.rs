@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use super::invariant::AGE_EPSILON;
+use super::place::in_interval;
+
+/// Token ages of every place, indexed like the places of the net
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Marking {
+    pub tokens: Vec<Vec<f64>>,
+}
+
+impl Marking {
+    pub fn new(tokens: Vec<Vec<f64>>) -> Marking {
+        Marking { tokens }
+    }
+
+    /// Marking with no tokens in any of `places` places
+    pub fn empty(places: usize) -> Marking {
+        Marking {
+            tokens: vec![Vec::new(); places],
+        }
+    }
+
+    pub fn tokens(&self, place: usize) -> &[f64] {
+        &self.tokens[place]
+    }
+
+    pub fn count(&self, place: usize) -> usize {
+        self.tokens[place].len()
+    }
+
+    pub fn tokens_hold(&self, place: usize, n: usize, timing: &[f64; 2]) -> bool {
+        // Count tokens within age bounds
+        let count = self.tokens[place]
+            .iter()
+            .filter(|&&age| in_interval(age, timing))
+            .count();
+
+        count >= n
+    }
+
+    /// Earliest positive delay at which a token of `place` enters or leaves `timing`
+    pub fn next_interval_change(&self, place: usize, timing: &[f64; 2]) -> f64 {
+        self.tokens[place]
+            .iter()
+            .flat_map(|&age| [timing[0] - age, timing[1] - age])
+            .filter(|&delay| delay > AGE_EPSILON)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Removes the tokens of `place` at the given indices and returns their ages in the same order
+    pub fn remove_tokens_at(&mut self, place: usize, indices: &[usize]) -> Vec<f64> {
+        let tokens = &mut self.tokens[place];
+        let removed = indices.iter().map(|&i| tokens[i]).collect();
+
+        // Remove from the back so the remaining indices stay valid
+        let mut sorted = indices.to_vec();
+        sorted.sort_unstable();
+        for i in sorted.into_iter().rev() {
+            tokens.remove(i);
+        }
+
+        removed
+    }
+
+    pub fn add_tokens(&mut self, place: usize, tokens: &[f64]) {
+        self.tokens[place].extend_from_slice(tokens);
+    }
+
+    /// Ages every token by `delay`
+    pub fn delay(&mut self, delay: f64) {
+        for token in self.tokens.iter_mut().flatten() {
+            *token += delay;
+        }
+    }
+}
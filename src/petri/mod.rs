@@ -1,13 +1,26 @@
 pub mod arcs;
+pub mod binding;
+pub mod distribution;
 pub mod invariant;
+pub mod marking;
 pub mod net;
 pub mod place;
+pub mod rng;
+#[cfg(test)]
+pub(crate) mod testing;
 pub mod transition;
+pub mod validate;
 
 pub use arcs::{
-    ArcType, InhibitorArc, InputArc, OutputArc, RegularOutputArc, TransportArc, TransportOutputArc,
+    ArcType, InhibitorArc, InputArc, OutputArc, RegularOutputArc, TokenSelection, TransportArc,
+    TransportOutputArc,
 };
+pub use binding::{Binding, FiringError};
+pub use distribution::{Distribution, DistributionError, Sampler};
 pub use invariant::Invariant;
-pub use net::Tapn;
+pub use marking::Marking;
+pub use net::{RunOutcome, RunResult, StepOutcome, Tapn};
 pub use place::Place;
-pub use transition::{Distribution, Transition};
+pub use rng::{SimulationRng, derive_seed};
+pub use transition::{MemoryPolicy, Transition};
+pub use validate::{Diagnostic, DiagnosticKind, NetElement, ValidationError};
@@ -1,29 +1,169 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
 
-use crate::{Place, SimulationEvent, SimulationObserver, Transition};
+use rand::Rng;
+use rand::seq::IndexedRandom;
 
+use super::invariant::AGE_EPSILON;
+use super::rng::{self, SimulationRng};
+use crate::{
+    Marking, Place, SimulationEvent, SimulationObserver, TapnError, Transition, ValidationError,
+};
+
+/// A timed-arc Petri net together with its current marking and simulation state.
+/// Arcs refer to places by index, so the whole net is `Clone + Send + Sync`.
+#[derive(Clone)]
 pub struct Tapn {
-    pub places: Vec<Rc<RefCell<Place>>>,
+    pub places: Vec<Place>,
     pub transitions: Vec<Transition>,
+    pub marking: Marking,
+    /// Marking the net started from, restored by `reset`
+    pub initial_marking: Marking,
     pub observers: Vec<Box<dyn SimulationObserver>>,
     pub steps: usize,
     pub current_time: f64,
     pub transition_firings: HashMap<usize, usize>,
+    /// Global time at which simulation stops, no transition fires later than this
+    pub time_limit: Option<f64>,
+    /// Number of firings after which simulation stops
+    pub step_limit: Option<usize>,
+    /// Seed the random streams of this net were derived from
+    pub seed: u64,
+    /// Stream used for choices made by the net itself, such as conflicts between transitions
+    rng: SimulationRng,
+    /// One independent stream per transition, indexed like `transitions`
+    transition_rngs: Vec<SimulationRng>,
+}
+
+/// What happened during a single call to `Tapn::step`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepOutcome {
+    /// The transition with the given index fired at the global time `time`
+    Fired { transition: usize, time: f64 },
+    /// No transition can ever become enabled and time may pass forever
+    Deadlock,
+    /// No transition is enabled but place invariants forbid time from passing
+    TimeLock,
+    /// Nothing fires before the time limit, time has advanced up to it
+    TimeLimit,
+    /// The net has already fired as many times as the step limit allows
+    StepLimit,
+}
+
+/// Why a call to `Tapn::run` stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The observer at this index of `Tapn::observers` asked to stop
+    Stopped { observer: usize },
+    /// No transition can ever become enabled and time may pass forever
+    Deadlock,
+    /// No transition is enabled but place invariants forbid time from passing
+    TimeLock,
+    /// The global time reached `Tapn::time_limit`
+    TimeLimit,
+    /// The number of firings reached `Tapn::step_limit`
+    StepLimit,
+}
+
+/// Summary of a single call to `Tapn::run`
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    /// Seed that reproduces this run when passed to `Tapn::with_seed`
+    pub seed: u64,
+    pub outcome: RunOutcome,
+    pub steps: usize,
+    pub end_time: f64,
+    /// Number of times each transition fired, indexed like `transitions`
+    pub firings: Vec<usize>,
 }
 
 impl Tapn {
+    /// Builds a net from its places, transitions and initial marking,
+    /// or returns everything `validate` finds wrong with it
     pub fn new(
-        places: Vec<Rc<RefCell<Place>>>,
+        places: Vec<Place>,
         transitions: Vec<Transition>,
-    ) -> Self {
-        Self {
+        marking: Marking,
+    ) -> Result<Self, ValidationError> {
+        let seed = rand::rng().random();
+        let mut tapn = Self {
             places,
             transitions,
+            initial_marking: marking.clone(),
+            marking,
             observers: Vec::new(),
             steps: 0,
             current_time: 0.0,
             transition_firings: HashMap::new(),
+            time_limit: None,
+            step_limit: None,
+            seed,
+            rng: rng::stream(seed, 0),
+            transition_rngs: Vec::new(),
+        };
+        tapn.reseed(seed);
+
+        let diagnostics = tapn.validate();
+        if diagnostics.is_empty() {
+            Ok(tapn)
+        } else {
+            Err(ValidationError { diagnostics })
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.reseed(seed);
+        self
+    }
+
+    pub fn with_time_limit(mut self, time_limit: f64) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    pub fn with_step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+
+    /// Restarts every random stream of the net from `seed`
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        // Stream 0 belongs to the net itself, transitions get the following ones
+        self.rng = rng::stream(seed, 0);
+        self.transition_rngs = (0..self.transitions.len())
+            .map(|i| rng::stream(seed, i as u64 + 1))
+            .collect();
+    }
+
+    /// Gives transitions added since the last `reseed` the streams `reseed` would have given them
+    fn grow_streams(&mut self) {
+        let seed = self.seed;
+        let known = self.transition_rngs.len();
+        self.transition_rngs.truncate(self.transitions.len());
+        self.transition_rngs
+            .extend((known..self.transitions.len()).map(|i| rng::stream(seed, i as u64 + 1)));
+    }
+
+    /// Puts the net back into its initial state so it can be run again without rebuilding it.
+    /// The random streams move on to a seed derived from the current one, so consecutive runs
+    /// differ while the seed recorded for each run still reproduces it.
+    pub fn reset(&mut self) {
+        self.marking = self.initial_marking.clone();
+        self.current_time = 0.0;
+        self.steps = 0;
+        self.transition_firings.clear();
+
+        for transition in &mut self.transitions {
+            transition.reset();
+        }
+
+        for observer in &mut self.observers {
+            observer.reset();
         }
+
+        // The first stream not used by the net itself
+        let next_stream = self.transitions.len() as u64 + 1;
+        self.reseed(rng::derive_seed(self.seed, next_stream));
     }
 
     pub fn add_observer(&mut self, observer: Box<dyn SimulationObserver>) {
@@ -36,42 +176,145 @@ impl Tapn {
         }
     }
 
-    pub fn step(&mut self) -> bool {
-        // Update enabled status of all transitions
-        self.update_enabled_transitions();
+    pub fn step(&mut self) -> Result<StepOutcome, TapnError> {
+        if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+            return Ok(StepOutcome::StepLimit);
+        }
 
-        // Find all enabled transitions with their firing times
-        let enabled_transitions: Vec<_> = self
-            .transitions
-            .iter_mut()
-            .enumerate()
-            .filter_map(|(i, t)| t.is_ready().then_some((i, t.firing_time)))
-            .collect();
+        self.grow_streams();
+
+        loop {
+            // Sample firing times for newly enabled transitions and drop those of disabled ones
+            let mut enabled_transitions = Vec::new();
+            let transitions = self.transitions.iter_mut().zip(&mut self.transition_rngs);
+            for (transition, (t, rng)) in transitions.enumerate() {
+                let ready = t
+                    .is_ready(&self.marking, &self.places, rng)
+                    .map_err(|error| TapnError::Sampling { transition, error })?;
+                if ready {
+                    enabled_transitions.push(transition);
+                }
+            }
 
-        if enabled_transitions.is_empty() {
-            return false;
+            // Time may not pass while an urgent transition is enabled, so one of them fires now
+            let urgent: Vec<_> = enabled_transitions
+                .iter()
+                .copied()
+                .filter(|&i| self.transitions[i].urgent)
+                .collect();
+            if let Some(urgent_idx) = self.resolve_conflict(&urgent) {
+                return self.fire_transition(urgent_idx);
+            }
+
+            // Place invariants bound how long we may wait for the next firing
+            let max_delay = self.max_delay();
+
+            // Enabledness stays the same until the next time a token crosses a guard bound,
+            // so the transitions still enabled halfway there race until that point
+            let next_change = self.next_enabling_change();
+            let probe = if next_change.is_finite() { next_change / 2.0 } else { 1.0 };
+            let racing: Vec<_> = enabled_transitions
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    self.transitions[i].is_enabled_after(&self.marking, &self.places, probe)
+                })
+                .collect();
+
+            // The transition with the smallest remaining firing time wins the race.
+            // Transitions that are only enabled right now can still fire without delay.
+            let candidates: Vec<_> = enabled_transitions
+                .iter()
+                .map(|&i| (i, self.transitions[i].firing_time.unwrap_or(0.0)))
+                .filter(|&(i, firing_time)| firing_time <= 0.0 || racing.contains(&i))
+                .collect();
+            let firing_time = candidates
+                .iter()
+                .map(|&(_, firing_time)| firing_time)
+                .fold(f64::INFINITY, f64::min);
+            let tied: Vec<_> = candidates
+                .iter()
+                .filter(|&&(_, t)| t <= firing_time + AGE_EPSILON)
+                .map(|&(i, _)| i)
+                .collect();
+            let winner = self.resolve_conflict(&tied);
+
+            let delay = firing_time.min(max_delay).min(next_change);
+
+            if delay.is_infinite() {
+                return Ok(StepOutcome::Deadlock);
+            }
+
+            // Whatever happens next would happen after the time limit
+            if let Some(limit) = self.time_limit
+                && self.current_time + delay > limit
+            {
+                self.delay(limit - self.current_time);
+                return Ok(StepOutcome::TimeLimit);
+            }
+
+            // Every racing transition has been enabled for the whole delay
+            for &i in &racing {
+                self.transitions[i].elapse(delay);
+            }
+
+            match winner {
+                // The winner fires when its time is up, or is forced to when an invariant runs out
+                Some(transition_idx) if delay == firing_time || delay == max_delay => {
+                    self.delay(delay);
+                    return self.fire_transition(transition_idx);
+                }
+                // Nothing can fire before the invariants stop time
+                _ if delay == max_delay && delay < next_change => {
+                    self.delay(delay);
+                    return Ok(StepOutcome::TimeLock);
+                }
+                // Wait for the guards to change and look again
+                _ => self.delay(delay),
+            }
         }
+    }
 
-        // Handle urgent transitions first
-        if let Some(&(urgent_idx, _)) = enabled_transitions
+    /// Picks one of the conflicting transitions: the highest priority wins,
+    /// remaining ties are broken at random in proportion to the transition weights
+    fn resolve_conflict(&mut self, candidates: &[usize]) -> Option<usize> {
+        let priority = candidates
             .iter()
-            .find(|&&(i, _)| self.transitions[i].urgent)
-        {
-            return self.fire_transition(urgent_idx);
+            .map(|&i| self.transitions[i].priority)
+            .max()?;
+        let highest: Vec<_> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| self.transitions[i].priority == priority)
+            .collect();
+
+        match highest.choose_weighted(&mut self.rng, |&i| self.transitions[i].weight) {
+            Ok(&i) => Some(i),
+            // All weights are zero, fall back to a uniform choice
+            Err(_) => highest.choose(&mut self.rng).copied(),
         }
+    }
 
-        // Find the transition with minimal firing time
-        let (transition_idx, delay) = enabled_transitions
+    /// Longest delay allowed by the invariants of all places
+    pub fn max_delay(&self) -> f64 {
+        self.places
             .iter()
-            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .unwrap();
+            .zip(&self.marking.tokens)
+            .map(|(place, tokens)| place.max_delay(tokens))
+            .fold(f64::INFINITY, f64::min)
+    }
 
-        self.delay(*delay);
-        self.fire_transition(*transition_idx)
+    /// Earliest positive delay at which any transition may become enabled or disabled
+    pub fn next_enabling_change(&self) -> f64 {
+        self.transitions
+            .iter()
+            .map(|t| t.next_enabling_change(&self.marking, &self.places))
+            .fold(f64::INFINITY, f64::min)
     }
 
-    fn fire_transition(&mut self, index: usize) -> bool {
-        let firing_time = self.transitions[index].firing_time;
+    /// Fires a transition at the current time, time has to be advanced with `delay` beforehand
+    fn fire_transition(&mut self, index: usize) -> Result<StepOutcome, TapnError> {
+        let firing_time = self.current_time;
 
         // Notify before firing
         let pre_event = SimulationEvent::TransitionFiring {
@@ -80,28 +323,34 @@ impl Tapn {
         };
         self.notify_observers(pre_event);
 
-        // Update token ages
-        self.update_token_ages(firing_time);
-
         // Fire the transition and capture consumed tokens
         let consumed_tokens = {
             // Isolate the transition borrow
             let transition = &mut self.transitions[index];
-            transition.fire()
+            transition.fire(
+                &mut self.marking,
+                &self.places,
+                &mut self.transition_rngs[index],
+            )?
         };
 
+        // Resampling transitions lose their firing times whenever something fires
+        for (i, transition) in self.transitions.iter_mut().enumerate() {
+            if i != index {
+                transition.on_other_fired();
+            }
+        }
+
         // Record the firing
         *self.transition_firings.entry(index).or_insert(0) += 1;
         self.steps += 1;
 
-        // Collect place states after firing
-        let place_states: HashMap<_, _> = self
+        // Collect place states after firing, in place order so runs are reproducible
+        let place_states: Vec<_> = self
             .places
             .iter()
-            .map(|p| {
-                let place = p.borrow();
-                (place.id, place.tokens.clone())
-            })
+            .zip(&self.marking.tokens)
+            .map(|(place, tokens)| (place.id, tokens.clone()))
             .collect();
 
         // Notify about token changes
@@ -120,7 +369,10 @@ impl Tapn {
         };
         self.notify_observers(post_event);
 
-        true
+        Ok(StepOutcome::Fired {
+            transition: index,
+            time: self.current_time,
+        })
     }
 
     // Query method
@@ -128,48 +380,249 @@ impl Tapn {
         *self.transition_firings.get(&transition_id).unwrap_or(&0)
     }
 
-    pub fn run(&mut self) {
-        while !self.should_continue() {
-            self.step();
-        }
+    /// Simulates until an observer stops the run or no further step is possible,
+    /// because of a deadlock, a time-lock or a limit
+    pub fn run(&mut self) -> Result<RunResult, TapnError> {
+        let outcome = loop {
+            if let Some(observer) = self.stopping_observer() {
+                break RunOutcome::Stopped { observer };
+            }
+
+            match self.step()? {
+                StepOutcome::Fired { .. } => {}
+                StepOutcome::Deadlock => break RunOutcome::Deadlock,
+                StepOutcome::TimeLock => break RunOutcome::TimeLock,
+                StepOutcome::TimeLimit => break RunOutcome::TimeLimit,
+                StepOutcome::StepLimit => break RunOutcome::StepLimit,
+            }
+        };
 
         for observer in &mut self.observers {
-            observer.on_completion();
+            observer.on_completion(&outcome);
         }
+
+        Ok(RunResult {
+            seed: self.seed,
+            outcome,
+            steps: self.steps,
+            end_time: self.current_time,
+            firings: (0..self.transitions.len())
+                .map(|i| self.firing_count(i))
+                .collect(),
+        })
     }
 
+    /// Checks if any observer asks to stop the run
     pub fn should_continue(&mut self) -> bool {
-        self.observers.iter().any(|o| o.should_stop())
+        self.stopping_observer().is_some()
     }
 
-    fn delay(&mut self, delay: f64) {
-        // Advance the simulation time by the delay
-        // This can be tracked as a field in the Tapn struct if needed
+    /// Index of the first observer that asks to stop
+    pub fn stopping_observer(&self) -> Option<usize> {
+        self.observers.iter().position(|o| o.should_stop())
     }
 
-    fn update_token_ages(&mut self, delay: f64) {
+    /// Lets `delay` time units pass: advances the global clock and ages every token once.
+    /// Sampled firing times are left alone, `step` takes care of those.
+    pub fn delay(&mut self, delay: f64) {
         if delay <= 0.0 {
             return;
         }
 
         self.current_time += delay;
 
-        for place in &mut self.places {
-            let mut place = place.borrow_mut();
-            for token in &mut place.tokens {
-                *token += delay;
-            }
-        }
+        self.marking.delay(delay);
 
         self.notify_observers(SimulationEvent::TimeAdvanced {
             delta: delay,
             new_time: self.current_time,
         });
     }
+}
 
-    fn update_enabled_transitions(&mut self) {
-        for transition in &mut self.transitions {
-            transition.is_ready();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Distribution;
+    use crate::petri::testing::{places, transfer};
+    use crate::{ArcType, InputArc, Invariant, MemoryPolicy, TokenSelection};
+
+    /// Consumes the token of `P0` and puts it back after a uniform delay
+    fn cycle(id: usize) -> Transition {
+        transfer(0, 0, Distribution::Uniform(0.0, 1.0), id)
+    }
+
+    /// Moves a token from `input` to `output` after exactly `delay` time units
+    fn constant(input: usize, output: usize, delay: f64, id: usize) -> Transition {
+        transfer(input, output, Distribution::Constant(delay), id)
+    }
+
+    /// Only lets the transition take tokens whose age lies in `timing`
+    fn guarded(mut transition: Transition, timing: [f64; 2]) -> Transition {
+        if let ArcType::Input(arc) = &mut transition.input_arcs[0] {
+            arc.timing = timing;
+        }
+        transition
+    }
+
+    /// Steps until nothing fires anymore and returns every firing with its global time
+    fn firings(tapn: &mut Tapn) -> Vec<(usize, f64)> {
+        let mut firings = Vec::new();
+        while let StepOutcome::Fired { transition, time } = tapn.step().unwrap() {
+            firings.push((transition, time));
+        }
+        firings
+    }
+
+    fn net(seed: u64) -> Tapn {
+        Tapn::new(places(1), vec![cycle(0)], Marking::new(vec![vec![0.0]]))
+            .unwrap()
+            .with_seed(seed)
+            .with_step_limit(50)
+    }
+
+    #[test]
+    fn same_seed_gives_same_run() {
+        let first = net(7).run().unwrap();
+        let second = net(7).run().unwrap();
+        assert_eq!(first.steps, 50);
+        assert_eq!(first.end_time, second.end_time);
+        assert_eq!(first.firings, second.firings);
+
+        let other = net(8).run().unwrap();
+        assert_ne!(first.end_time, other.end_time);
+    }
+
+    #[test]
+    fn reset_and_reseed_reproduce_a_run() {
+        let mut tapn = net(7);
+        let first = tapn.run().unwrap();
+        tapn.reset();
+        tapn.reseed(7);
+        let second = tapn.run().unwrap();
+        assert_eq!(first.end_time, second.end_time);
+    }
+
+    #[test]
+    fn transitions_added_after_construction_fire() {
+        let mut tapn = net(7);
+        tapn.transitions.push(cycle(1));
+        let result = tapn.run().unwrap();
+        assert_eq!(result.firings.len(), 2);
+        assert!(result.firings[1] > 0);
+    }
+
+    /// `T1` is sampled 3 while `T0` fires at 1 without touching its tokens
+    fn resampling(policy: MemoryPolicy) -> Vec<(usize, f64)> {
+        let transitions = vec![
+            constant(0, 1, 1.0, 0),
+            constant(2, 3, 3.0, 1).with_memory_policy(policy),
+        ];
+        let marking = Marking::new(vec![vec![0.0], Vec::new(), vec![0.0], Vec::new()]);
+        firings(&mut Tapn::new(places(4), transitions, marking).unwrap())
+    }
+
+    #[test]
+    fn resampling_draws_a_new_delay_after_every_firing() {
+        assert_eq!(
+            resampling(MemoryPolicy::EnablingMemory),
+            [(0, 1.0), (1, 3.0)]
+        );
+        assert_eq!(resampling(MemoryPolicy::AgeMemory), [(0, 1.0), (1, 3.0)]);
+        assert_eq!(resampling(MemoryPolicy::Resampling), [(0, 1.0), (1, 4.0)]);
+    }
+
+    /// `T0` takes the token of `P0` away from `T1` at 1 and `T2` brings it back at 2,
+    /// with 2 of the 3 time units `T1` was sampled still left
+    fn disabled(policy: MemoryPolicy) -> Vec<(usize, f64)> {
+        // The token of `P3` lets `T0` fire only once
+        let mut steal = constant(0, 1, 1.0, 0);
+        steal.input_arcs.push(ArcType::Input(InputArc {
+            input: 3,
+            weight: 1,
+            timing: [0.0, f64::INFINITY],
+            selection: TokenSelection::default(),
+        }));
+        let transitions = vec![
+            steal,
+            constant(0, 2, 3.0, 1).with_memory_policy(policy),
+            constant(1, 0, 1.0, 2),
+        ];
+        let marking = Marking::new(vec![vec![0.0], Vec::new(), Vec::new(), vec![0.0]]);
+        firings(&mut Tapn::new(places(4), transitions, marking).unwrap())
+    }
+
+    #[test]
+    fn only_age_memory_keeps_the_delay_while_disabled() {
+        assert_eq!(
+            disabled(MemoryPolicy::EnablingMemory),
+            [(0, 1.0), (2, 2.0), (1, 5.0)]
+        );
+        assert_eq!(
+            disabled(MemoryPolicy::Resampling),
+            [(0, 1.0), (2, 2.0), (1, 5.0)]
+        );
+        assert_eq!(
+            disabled(MemoryPolicy::AgeMemory),
+            [(0, 1.0), (2, 2.0), (1, 4.0)]
+        );
+    }
+
+    /// A token in `P0` that may get no older than 2
+    fn bounded(transitions: Vec<Transition>) -> Tapn {
+        let mut places = places(2);
+        places[0].invariants.push(Invariant::at_most(2.0));
+        let marking = Marking::new(vec![vec![0.0], Vec::new()]);
+        Tapn::new(places, transitions, marking).unwrap()
+    }
+
+    #[test]
+    fn invariants_force_enabled_transitions_to_fire() {
+        let mut tapn = bounded(vec![constant(0, 1, 10.0, 0)]);
+        assert_eq!(firings(&mut tapn), [(0, 2.0)]);
+    }
+
+    #[test]
+    fn invariants_stop_time_when_nothing_can_fire() {
+        let mut tapn = bounded(vec![guarded(constant(0, 1, 1.0, 0), [3.0, f64::INFINITY])]);
+        assert_eq!(tapn.step().unwrap(), StepOutcome::TimeLock);
+        assert_eq!(tapn.current_time, 2.0);
+        assert_eq!(tapn.marking.tokens(0), [2.0]);
+    }
+
+    #[test]
+    fn urgent_transitions_fire_as_soon_as_they_are_enabled() {
+        // The sampled delay of an urgent transition is ignored
+        let mut urgent = guarded(constant(0, 1, 3.0, 0), [1.0, f64::INFINITY]);
+        urgent.urgent = true;
+        let transitions = vec![urgent, constant(0, 2, 5.0, 1)];
+        let marking = Marking::new(vec![vec![0.0], Vec::new(), Vec::new()]);
+        let mut tapn = Tapn::new(places(3), transitions, marking).unwrap();
+        assert_eq!(firings(&mut tapn), [(0, 1.0)]);
+    }
+
+    /// `T0` and `T1` both want the token of `P0` after exactly 1 time unit
+    fn conflict(first: Transition, second: Transition) -> Tapn {
+        let marking = Marking::new(vec![vec![0.0], Vec::new(), Vec::new()]);
+        Tapn::new(places(3), vec![first, second], marking).unwrap()
+    }
+
+    #[test]
+    fn the_highest_priority_wins_a_tie() {
+        let preferred = constant(0, 2, 1.0, 1).with_priority(1);
+        let tapn = conflict(constant(0, 1, 1.0, 0), preferred);
+        for seed in 0..20 {
+            assert_eq!(firings(&mut tapn.clone().with_seed(seed)), [(1, 1.0)]);
         }
     }
+
+    #[test]
+    fn weights_split_ties_in_proportion() {
+        let heavy = constant(0, 1, 1.0, 0).with_weight(3.0);
+        let tapn = conflict(heavy, constant(0, 2, 1.0, 1));
+        let wins = (0..1000)
+            .filter(|&seed| firings(&mut tapn.clone().with_seed(seed)) == [(0, 1.0)])
+            .count();
+        assert!((700..800).contains(&wins), "{wins} of 1000 ties won");
+    }
 }
@@ -1,82 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::invariant::AGE_EPSILON;
 use crate::Invariant;
 
-#[derive(Clone)]
+/// Static description of a place, its tokens live in a `Marking`
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Place {
     pub id: usize,
     pub name: String,
-    pub tokens: Vec<f64>,
     pub invariants: Vec<Invariant>,
 }
 
 impl Place {
-    pub fn new(id: usize, name: String, tokens: Vec<f64>, invariants: Vec<Invariant>) -> Place {
+    pub fn new(id: usize, name: String, invariants: Vec<Invariant>) -> Place {
         Place {
             id,
             name,
-            tokens,
             invariants,
         }
     }
 
-    pub fn invariants_hold(&self, n: usize) -> bool {
-        // If `n` is greater than the number of tokens, it's impossible to satisfy the condition.
-        if n > self.tokens.len() {
-            return false;
-        }
-
-        // If there are no invariants then all tokens hold the invariant
-        if self.invariants.len() == 0 {
-            return true;
-        }
-
-        // Count how many tokens satisfy all invariants.
-        let valid_tokens_count = self
-            .tokens
+    /// Longest delay allowed before one of `tokens` in this place violates an invariant
+    pub fn max_delay(&self, tokens: &[f64]) -> f64 {
+        tokens
             .iter()
-            .filter(|token| {
-                self.invariants
-                    .iter()
-                    .all(|invariant| invariant.check(**token)) // We have to de-refference the token twice because of filter
-            })
-            .count();
-
-        // Check if at least `n` tokens satisfy all invariants.
-        valid_tokens_count >= n
+            .flat_map(|&age| self.invariants.iter().map(move |inv| inv.max_delay(age)))
+            .fold(f64::INFINITY, f64::min)
     }
 
-    pub fn tokens_hold(&self, n: usize, timing: &[f64; 2]) -> bool {
-        let min_age = timing[0];
-        let max_age = timing[1];
-
-        // Count tokens within age bounds
-        let count = self
-            .tokens
+    /// Highest token age allowed by the invariants of this place
+    pub fn invariant_bound(&self) -> f64 {
+        self.invariants
             .iter()
-            .filter(|&age| *age >= min_age && *age <= max_age)
-            .count();
-
-        count >= n
-    }
-
-    pub fn remove_tokens(&mut self, n: usize) -> Vec<f64> {
-        let mut tokens = Vec::with_capacity(n);
-
-        // Early return if we don't have enough tokens
-        if self.tokens.len() < n {
-            return tokens;
-        }
-
-        // Sort tokens by age (youngest first)
-        self.tokens
-            .sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Remove the n oldest tokens (from the end after sorting)
-        tokens.extend(self.tokens.drain(self.tokens.len() - n..));
-
-        tokens
+            .map(|inv| inv.upper_bound())
+            .fold(f64::INFINITY, f64::min)
     }
+}
 
-    pub fn add_tokens(&mut self, tokens: &[f64]) {
-        self.tokens.extend_from_slice(tokens);
-    }
+/// Checks if a token age lies within the closed interval `timing`
+pub fn in_interval(age: f64, timing: &[f64; 2]) -> bool {
+    age >= timing[0] - AGE_EPSILON && age <= timing[1] + AGE_EPSILON
 }
@@ -0,0 +1,19 @@
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Random number generator used for all sampling in a simulation
+pub type SimulationRng = StdRng;
+
+/// Derives the seed of an independent stream from a base seed.
+/// Uses the SplitMix64 finalizer so neighbouring streams are uncorrelated.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    let mut z = seed
+        .wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Creates the generator for the given stream of a base seed
+pub fn stream(seed: u64, stream: u64) -> SimulationRng {
+    SimulationRng::seed_from_u64(derive_seed(seed, stream))
+}
@@ -0,0 +1,46 @@
+//! Building blocks for the nets used in unit tests
+
+use crate::{
+    ArcType, Distribution, InputArc, Marking, OutputArc, Place, RegularOutputArc, Tapn,
+    TokenSelection, Transition,
+};
+
+/// Places without invariants, named `P0`, `P1` and so on
+pub(crate) fn places(count: usize) -> Vec<Place> {
+    (0..count)
+        .map(|id| Place::new(id, format!("P{id}"), Vec::new()))
+        .collect()
+}
+
+/// Moves a token of any age from `input` to `output` after a delay drawn from `distribution`
+pub(crate) fn transfer(
+    input: usize,
+    output: usize,
+    distribution: Distribution,
+    id: usize,
+) -> Transition {
+    Transition::new(
+        vec![ArcType::Input(InputArc {
+            input,
+            weight: 1,
+            timing: [0.0, f64::INFINITY],
+            selection: TokenSelection::default(),
+        })],
+        vec![OutputArc::Regular(RegularOutputArc { output, weight: 1 })],
+        distribution,
+        false,
+        id,
+    )
+}
+
+/// Moves the token of `P0` to `P1` after a uniform delay on `[0, 1]`, so it has arrived by
+/// time `t` with probability `t`
+pub(crate) fn uniform_transfer() -> Tapn {
+    let transition = transfer(0, 1, Distribution::Uniform(0.0, 1.0), 0);
+    Tapn::new(
+        places(2),
+        vec![transition],
+        Marking::new(vec![vec![0.0], Vec::new()]),
+    )
+    .unwrap()
+}
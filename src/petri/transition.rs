@@ -1,103 +1,205 @@
-use super::{OutputArc, arcs::ArcType};
+use std::collections::HashMap;
+
+use super::binding::{self, FiringError};
+use super::{
+    Distribution, DistributionError, Marking, OutputArc, Place, Sampler, arcs::ArcType,
+};
 use rand::Rng;
-use rand::distr::Uniform;
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Transition {
     pub input_arcs: Vec<ArcType>,
     pub output_arcs: Vec<OutputArc>,
     pub distribution_function: Distribution,
-    pub firing_time: f64,
+    /// Remaining delay of the currently sampled firing time, `None` if no delay is sampled
+    #[serde(skip)]
+    pub firing_time: Option<f64>,
+    /// Built from `distribution_function` when the first delay is sampled
+    #[serde(skip)]
+    sampler: Option<Sampler>,
+    #[serde(default)]
+    pub memory_policy: MemoryPolicy,
+    /// Relative probability of winning a tie against transitions of the same priority
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// Ties between transitions are won by the highest priority
+    #[serde(default)]
+    pub priority: u32,
     pub urgent: bool,
     pub id: usize,
 }
 
+/// Decides what happens to a sampled firing delay when the net changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryPolicy {
+    /// A new delay is sampled after every firing in the net
+    Resampling,
+    /// The delay is kept while the transition stays enabled and discarded when it is disabled
+    #[default]
+    EnablingMemory,
+    /// The remaining delay is kept while disabled and only discarded when the transition fires
+    AgeMemory,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
 impl Transition {
     pub fn new(input_arcs: Vec<ArcType>, output_arcs: Vec<OutputArc>, distribution_function: Distribution, urgent: bool, id: usize) -> Transition {
         Transition {
             input_arcs,
             output_arcs,
             distribution_function,
-            firing_time: 0.0,
+            firing_time: None,
+            sampler: None,
+            memory_policy: MemoryPolicy::default(),
+            weight: 1.0,
+            priority: 0,
             urgent,
             id,
         }
     }
 
-    pub fn fire(&mut self) -> Vec<f64> {
+    pub fn with_memory_policy(mut self, memory_policy: MemoryPolicy) -> Self {
+        self.memory_policy = memory_policy;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Fires the transition as one transaction: the tokens of all input arcs are bound
+    /// first and only removed once the binding is complete
+    pub fn fire<R: Rng + ?Sized>(
+        &mut self,
+        marking: &mut Marking,
+        places: &[Place],
+        rng: &mut R,
+    ) -> Result<Vec<f64>, FiringError> {
+        let binding =
+            binding::bind(&self.input_arcs, &self.output_arcs, marking, places, self.id, rng)?;
         let mut consumed_tokens = Vec::new();
-        
-        // Process input arcs
-        for arc in &mut self.input_arcs {
-            match arc {
-                ArcType::Input(arc) => {
-                    let tokens = arc.fire();
-                    consumed_tokens.extend(tokens);
-                }
-                ArcType::Transport(arc) => {
-                    let tokens = arc.fire();
-                    consumed_tokens.extend(tokens);
-                }
-                ArcType::Inhibitor(_) => {} // Inhibitor arcs don't consume tokens
+        let mut transported = HashMap::new();
+
+        // Read every bound token before removing any, arcs may share a place
+        for (arc, indices) in self.input_arcs.iter().zip(&binding.indices) {
+            let place = marking.tokens(arc.place());
+            let tokens: Vec<f64> = indices.iter().map(|&i| place[i]).collect();
+            consumed_tokens.extend(&tokens);
+
+            // Transport arcs hand their tokens to the output arc of the same pair
+            if let ArcType::Transport(arc) = arc {
+                transported.insert(arc.pair, tokens);
             }
         }
 
-        if self.output_arcs.is_empty() {
-            return consumed_tokens;
+        // Commit the binding, removing the tokens of each place in one go
+        for (i, arc) in self.input_arcs.iter().enumerate() {
+            let place = arc.place();
+            if self.input_arcs[..i].iter().any(|a| a.place() == place) {
+                continue;
+            }
+
+            let indices: Vec<usize> = self.input_arcs[i..]
+                .iter()
+                .zip(&binding.indices[i..])
+                .filter(|(a, _)| a.place() == place)
+                .flat_map(|(_, indices)| indices.iter().copied())
+                .collect();
+            marking.remove_tokens_at(place, &indices);
         }
 
+        // The sampled delay has been used up
+        self.firing_time = None;
+
         // Process output arcs
-        for arc in &mut self.output_arcs {
+        for arc in &self.output_arcs {
             match arc {
                 OutputArc::TransportArc(arc) => {
-                    arc.fire(&consumed_tokens);
+                    // The binding made sure every pair has its tokens
+                    arc.fire(marking, &transported[&arc.pair]);
                 }
                 OutputArc::Regular(arc) => {
-                    // For regular arcs, we use the consumed tokens' ages
-                    arc.fire(&consumed_tokens);
+                    arc.fire(marking, &consumed_tokens);
                 }
             }
         }
 
-        consumed_tokens
+        Ok(consumed_tokens)
+    }
+
+    /// Checks if all input arcs can get their tokens at the same time
+    pub fn is_enabled(&self, marking: &Marking, places: &[Place]) -> bool {
+        self.is_enabled_after(marking, places, 0.0)
+    }
+
+    /// Checks if the transition would be enabled once `delay` time units have passed
+    pub fn is_enabled_after(&self, marking: &Marking, places: &[Place], delay: f64) -> bool {
+        binding::can_bind(&self.input_arcs, &self.output_arcs, marking, places, delay)
+    }
+
+    /// Earliest positive delay at which the transition may become enabled or disabled
+    pub fn next_enabling_change(&self, marking: &Marking, places: &[Place]) -> f64 {
+        binding::next_change(&self.input_arcs, &self.output_arcs, marking, places)
     }
 
-    pub fn is_ready(&mut self) -> bool {
-        // Check all input arcs
-        for input_arc in &self.input_arcs {
-            if !input_arc.can_fire() {
-                return false;
+    /// Checks if the transition is enabled and makes sure it holds a sampled firing time.
+    /// A delay is only sampled when the transition has none, so it is kept between steps.
+    /// Urgent transitions never sample, they fire as soon as they are enabled.
+    pub fn is_ready<R: Rng + ?Sized>(
+        &mut self,
+        marking: &Marking,
+        places: &[Place],
+        rng: &mut R,
+    ) -> Result<bool, DistributionError> {
+        if !self.is_enabled(marking, places) {
+            // Age memory keeps the remaining delay until the transition is enabled again
+            if self.memory_policy != MemoryPolicy::AgeMemory {
+                self.firing_time = None;
             }
+            return Ok(false);
         }
 
-        // Only generate firing time if not urgent
-        if !self.urgent {
-            self.generate_firing_time();
-        } else {
-            self.firing_time = 0.0;
+        if !self.urgent && self.firing_time.is_none() {
+            if self.sampler.is_none() {
+                self.sampler = Some(self.distribution_function.sampler()?);
+            }
+            if let Some(sampler) = &self.sampler {
+                self.firing_time = Some(sampler.sample(rng));
+            }
         }
 
-        true
+        Ok(true)
     }
 
-    fn generate_firing_time(&mut self) {
-        self.firing_time = self.distribution_function.sample();
+    /// Lets `delay` time units pass on the sampled firing time of an enabled transition
+    pub fn elapse(&mut self, delay: f64) {
+        if let Some(remaining) = &mut self.firing_time {
+            *remaining = (*remaining - delay).max(0.0);
+        }
     }
-}
 
-pub enum Distribution {
-    Constant(f64),
-    Uniform(f64, f64),
-}
+    /// Forgets the sampled firing time and the sampler, which is rebuilt from the current
+    /// distribution when the next delay is sampled
+    pub fn reset(&mut self) {
+        self.firing_time = None;
+        self.sampler = None;
+    }
 
-impl Distribution {
-    pub fn sample(&self) -> f64 {
-        match self {
-            Distribution::Constant(val) => *val,
-            Distribution::Uniform(min, max) => {
-                let mut rng = rand::rng();
-                let range = Uniform::new(*min, *max).unwrap();
-                rng.sample(range)
-            }
+    /// Called after another transition fired
+    pub fn on_other_fired(&mut self) {
+        if self.memory_policy == MemoryPolicy::Resampling {
+            self.firing_time = None;
         }
     }
 }
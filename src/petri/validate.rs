@@ -0,0 +1,388 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use super::binding;
+use crate::{ArcType, DistributionError, OutputArc, Tapn};
+
+/// Part of a net a diagnostic refers to, by index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetElement {
+    Place(usize),
+    Transition(usize),
+    InputArc { transition: usize, arc: usize },
+    OutputArc { transition: usize, arc: usize },
+    InitialMarking,
+}
+
+impl fmt::Display for NetElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetElement::Place(place) => write!(f, "place {place}"),
+            NetElement::Transition(transition) => write!(f, "transition {transition}"),
+            NetElement::InputArc { transition, arc } => {
+                write!(f, "input arc {arc} of transition {transition}")
+            }
+            NetElement::OutputArc { transition, arc } => {
+                write!(f, "output arc {arc} of transition {transition}")
+            }
+            NetElement::InitialMarking => write!(f, "initial marking"),
+        }
+    }
+}
+
+/// What is wrong with an element of a net
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    /// Another place or transition already has this ID
+    DuplicateId(usize),
+    /// An arc refers to a place index outside `Tapn::places`
+    UnknownPlace {
+        place: usize,
+        places: usize,
+    },
+    /// A guard is not a non-negative interval `[lower, upper]` with `lower <= upper`
+    InvalidInterval([f64; 2]),
+    /// An arc moves no tokens, or an inhibitor arc blocks on zero tokens
+    ZeroWeight,
+    InvalidDistribution(DistributionError),
+    /// The transport arcs with this pairing ID don't form one input and output pair of equal
+    /// weight
+    UnpairedTransport(usize),
+    /// Invariant bounds must not be negative
+    InvalidInvariant(f64),
+    /// Transition weights must be finite and non-negative
+    InvalidWeight(f64),
+    /// The marking doesn't have exactly one list of tokens per place
+    MarkingSize {
+        places: usize,
+        marking: usize,
+    },
+    /// Token ages must be finite and non-negative
+    InvalidTokenAge(f64),
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::DuplicateId(id) => write!(f, "ID {id} is already in use"),
+            DiagnosticKind::UnknownPlace { place, places } => {
+                write!(f, "refers to place {place} but the net has {places} places")
+            }
+            DiagnosticKind::InvalidInterval([lower, upper]) => {
+                write!(f, "invalid interval [{lower}, {upper}]")
+            }
+            DiagnosticKind::ZeroWeight => write!(f, "weight is zero"),
+            DiagnosticKind::InvalidDistribution(error) => write!(f, "{error}"),
+            DiagnosticKind::UnpairedTransport(pair) => write!(
+                f,
+                "transport arcs of pair {pair} don't form one input and output pair of equal weight"
+            ),
+            DiagnosticKind::InvalidInvariant(bound) => write!(f, "invalid invariant bound {bound}"),
+            DiagnosticKind::InvalidWeight(weight) => write!(f, "invalid weight {weight}"),
+            DiagnosticKind::MarkingSize { places, marking } => {
+                write!(f, "has {marking} places but the net has {places}")
+            }
+            DiagnosticKind::InvalidTokenAge(age) => write!(f, "invalid token age {age}"),
+        }
+    }
+}
+
+/// A single problem found by `Tapn::validate`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub element: NetElement,
+    pub kind: DiagnosticKind,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.element, self.kind)
+    }
+}
+
+/// A net that failed validation, with everything that is wrong with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid net")?;
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n  {diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn valid_interval(timing: &[f64; 2]) -> bool {
+    timing[0] >= 0.0 && timing[0] <= timing[1]
+}
+
+impl Tapn {
+    /// Checks the structure of the net and its initial marking, returning every problem found.
+    /// Simulating a net with diagnostics may panic or give meaningless results.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut report = |element, kind| diagnostics.push(Diagnostic { element, kind });
+        let places = self.places.len();
+
+        let mut ids = HashSet::new();
+        for (index, place) in self.places.iter().enumerate() {
+            if !ids.insert(place.id) {
+                report(
+                    NetElement::Place(index),
+                    DiagnosticKind::DuplicateId(place.id),
+                );
+            }
+            for invariant in &place.invariants {
+                if invariant.bound.is_nan() || invariant.bound < 0.0 {
+                    report(
+                        NetElement::Place(index),
+                        DiagnosticKind::InvalidInvariant(invariant.bound),
+                    );
+                }
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for (transition, t) in self.transitions.iter().enumerate() {
+            let element = NetElement::Transition(transition);
+            if !ids.insert(t.id) {
+                report(element, DiagnosticKind::DuplicateId(t.id));
+            }
+            if let Err(error) = t.distribution_function.validate() {
+                report(element, DiagnosticKind::InvalidDistribution(error));
+            }
+            if !t.weight.is_finite() || t.weight < 0.0 {
+                report(element, DiagnosticKind::InvalidWeight(t.weight));
+            }
+            if let Some(pair) = binding::unpaired(&t.input_arcs, &t.output_arcs) {
+                report(element, DiagnosticKind::UnpairedTransport(pair));
+            }
+
+            for (arc, input) in t.input_arcs.iter().enumerate() {
+                let element = NetElement::InputArc { transition, arc };
+                let (timing, weight) = match input {
+                    ArcType::Input(a) => (a.timing, a.weight),
+                    ArcType::Transport(a) => (a.timing, a.weight),
+                    ArcType::Inhibitor(a) => (a.timing, a.constraint),
+                };
+                if input.place() >= places {
+                    let place = input.place();
+                    report(element, DiagnosticKind::UnknownPlace { place, places });
+                }
+                if !valid_interval(&timing) {
+                    report(element, DiagnosticKind::InvalidInterval(timing));
+                }
+                if weight == 0 {
+                    report(element, DiagnosticKind::ZeroWeight);
+                }
+            }
+
+            for (arc, output) in t.output_arcs.iter().enumerate() {
+                let element = NetElement::OutputArc { transition, arc };
+                let (place, weight) = match output {
+                    OutputArc::Regular(a) => (a.output, a.weight),
+                    OutputArc::TransportArc(a) => (a.output, a.weight),
+                };
+                if place >= places {
+                    report(element, DiagnosticKind::UnknownPlace { place, places });
+                }
+                if weight == 0 {
+                    report(element, DiagnosticKind::ZeroWeight);
+                }
+            }
+        }
+
+        let marking = self.initial_marking.tokens.len();
+        if marking != places {
+            report(
+                NetElement::InitialMarking,
+                DiagnosticKind::MarkingSize { places, marking },
+            );
+        }
+        for (place, tokens) in self.initial_marking.tokens.iter().enumerate() {
+            for &age in tokens {
+                if !age.is_finite() || age < 0.0 {
+                    report(
+                        NetElement::Place(place),
+                        DiagnosticKind::InvalidTokenAge(age),
+                    );
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petri::testing::{places, transfer};
+    use crate::{Distribution, InputArc, Marking, Place, TokenSelection, Transition, TransportArc};
+
+    /// Everything wrong with a net of two places, the first of which holds a token
+    fn diagnostics(places: Vec<Place>, transitions: Vec<Transition>) -> Vec<Diagnostic> {
+        let marking = Marking::new(vec![vec![0.0], Vec::new()]);
+        match Tapn::new(places, transitions, marking) {
+            Ok(_) => Vec::new(),
+            Err(error) => error.diagnostics,
+        }
+    }
+
+    fn diagnostic(element: NetElement, kind: DiagnosticKind) -> Vec<Diagnostic> {
+        vec![Diagnostic { element, kind }]
+    }
+
+    fn constant(input: usize, output: usize) -> Transition {
+        transfer(input, output, Distribution::Constant(1.0), 0)
+    }
+
+    fn input_arc(transition: &mut Transition) -> &mut InputArc {
+        match &mut transition.input_arcs[0] {
+            ArcType::Input(arc) => arc,
+            _ => unreachable!("`transfer` starts with an input arc"),
+        }
+    }
+
+    #[test]
+    fn valid_nets_have_no_diagnostics() {
+        assert_eq!(diagnostics(places(2), vec![constant(0, 1)]), Vec::new());
+    }
+
+    #[test]
+    fn uniform_distributions_need_a_non_empty_range() {
+        for (min, max) in [(2.0, 1.0), (1.0, 1.0)] {
+            let transition = transfer(0, 1, Distribution::Uniform(min, max), 0);
+            assert_eq!(
+                diagnostics(places(2), vec![transition]),
+                diagnostic(
+                    NetElement::Transition(0),
+                    DiagnosticKind::InvalidDistribution(DistributionError::EmptyRange {
+                        distribution: "uniform",
+                        min,
+                        max,
+                    }),
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn ids_must_be_unique() {
+        let mut duplicated = places(2);
+        duplicated[1].id = 0;
+        assert_eq!(
+            diagnostics(duplicated, vec![constant(0, 1)]),
+            diagnostic(NetElement::Place(1), DiagnosticKind::DuplicateId(0))
+        );
+
+        let transitions = vec![constant(0, 1), constant(1, 0)];
+        assert_eq!(
+            diagnostics(places(2), transitions),
+            diagnostic(NetElement::Transition(1), DiagnosticKind::DuplicateId(0))
+        );
+    }
+
+    #[test]
+    fn arcs_must_point_to_existing_places() {
+        let unknown = DiagnosticKind::UnknownPlace {
+            place: 5,
+            places: 2,
+        };
+        assert_eq!(
+            diagnostics(places(2), vec![constant(5, 1)]),
+            diagnostic(
+                NetElement::InputArc {
+                    transition: 0,
+                    arc: 0
+                },
+                unknown.clone()
+            )
+        );
+        assert_eq!(
+            diagnostics(places(2), vec![constant(0, 5)]),
+            diagnostic(
+                NetElement::OutputArc {
+                    transition: 0,
+                    arc: 0
+                },
+                unknown
+            )
+        );
+    }
+
+    #[test]
+    fn guards_must_be_ordered_and_non_negative() {
+        for timing in [[2.0, 1.0], [-1.0, 1.0]] {
+            let mut transition = constant(0, 1);
+            input_arc(&mut transition).timing = timing;
+            assert_eq!(
+                diagnostics(places(2), vec![transition]),
+                diagnostic(
+                    NetElement::InputArc {
+                        transition: 0,
+                        arc: 0
+                    },
+                    DiagnosticKind::InvalidInterval(timing)
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn arcs_must_move_tokens() {
+        let mut transition = constant(0, 1);
+        input_arc(&mut transition).weight = 0;
+        assert_eq!(
+            diagnostics(places(2), vec![transition]),
+            diagnostic(
+                NetElement::InputArc {
+                    transition: 0,
+                    arc: 0
+                },
+                DiagnosticKind::ZeroWeight
+            )
+        );
+    }
+
+    #[test]
+    fn transport_arcs_must_come_in_pairs() {
+        let mut transition = constant(0, 1);
+        transition.input_arcs.push(ArcType::Transport(TransportArc {
+            input: 1,
+            weight: 1,
+            timing: [0.0, f64::INFINITY],
+            selection: TokenSelection::default(),
+            pair: 3,
+        }));
+        assert_eq!(
+            diagnostics(places(2), vec![transition]),
+            diagnostic(
+                NetElement::Transition(0),
+                DiagnosticKind::UnpairedTransport(3)
+            )
+        );
+    }
+
+    #[test]
+    fn markings_need_one_list_of_tokens_per_place() {
+        let marking = Marking::new(vec![vec![0.0]]);
+        let result = Tapn::new(places(3), vec![constant(0, 1)], marking);
+        assert_eq!(
+            result.err().unwrap().diagnostics,
+            diagnostic(
+                NetElement::InitialMarking,
+                DiagnosticKind::MarkingSize {
+                    places: 3,
+                    marking: 1
+                }
+            )
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{Comparison, Marking, RunCheck, RunOutcome, StepOutcome, Tapn, TapnError};
+
+/// A query in TAPAAL syntax, such as `EF (finished >= 1)` or `PF<=10 (finished >= 1)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub quantifier: Quantifier,
+    pub formula: Formula,
+}
+
+/// Path quantifier of a query. Simulations follow a single run, so `E` and `A` both
+/// describe that run and only differ in which answer the query is after.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantifier {
+    /// `EF` or `E<>`: the formula holds at some point of the run
+    ExistsEventually,
+    /// `AG` or `A[]`: the formula holds throughout the run
+    AlwaysGlobally,
+    /// `PF<=t`: the formula holds at some point no later than `t`
+    ProbablyEventually(f64),
+    /// `PG<=t`: the formula holds throughout the run up to `t`
+    ProbablyGlobally(f64),
+}
+
+/// State formula evaluated over a single marking
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formula {
+    Bool(bool),
+    Compare(Term, Comparison, Term),
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+}
+
+/// Operand of a comparison
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Term {
+    /// Number of tokens in the place with this index
+    Place(usize),
+    Constant(usize),
+}
+
+impl Term {
+    pub fn value(&self, marking: &Marking) -> usize {
+        match self {
+            Term::Place(place) => marking.count(*place),
+            Term::Constant(value) => *value,
+        }
+    }
+}
+
+impl Formula {
+    pub fn holds(&self, marking: &Marking) -> bool {
+        match self {
+            Formula::Bool(value) => *value,
+            Formula::Compare(left, comparison, right) => {
+                comparison.holds(left.value(marking), right.value(marking))
+            }
+            Formula::Not(formula) => !formula.holds(marking),
+            Formula::And(formulas) => formulas.iter().all(|f| f.holds(marking)),
+            Formula::Or(formulas) => formulas.iter().any(|f| f.holds(marking)),
+        }
+    }
+}
+
+impl Quantifier {
+    /// Time bound of probabilistic quantifiers
+    pub fn time_bound(&self) -> Option<f64> {
+        match self {
+            Quantifier::ProbablyEventually(bound) | Quantifier::ProbablyGlobally(bound) => {
+                Some(*bound)
+            }
+            Quantifier::ExistsEventually | Quantifier::AlwaysGlobally => None,
+        }
+    }
+
+    /// Checks if the quantifier looks for a marking satisfying the formula,
+    /// rather than requiring it of every marking
+    pub fn is_eventually(&self) -> bool {
+        matches!(
+            self,
+            Quantifier::ExistsEventually | Quantifier::ProbablyEventually(_)
+        )
+    }
+}
+
+impl Query {
+    /// Answer of the query for a run that has reached `marking` at `time`,
+    /// or `None` while the run still has to go on
+    pub fn decide(&self, marking: &Marking, time: f64) -> Option<bool> {
+        let eventually = self.quantifier.is_eventually();
+
+        // Nothing after the time bound counts, the answer is what it was before
+        if self.quantifier.time_bound().is_some_and(|bound| time > bound) {
+            return Some(!eventually);
+        }
+
+        // Eventually stops at the first satisfying marking, globally at the first violating one
+        (self.formula.holds(marking) == eventually).then_some(eventually)
+    }
+
+    /// Answer of the query for a run that ended with `outcome` at `time` before `decide`
+    /// answered it, `None` if a limit or another observer cut the run off too early
+    pub fn conclude(&self, outcome: &RunOutcome, time: f64) -> Option<bool> {
+        let complete = match outcome {
+            // The marking can't change any more
+            RunOutcome::Deadlock | RunOutcome::TimeLock => true,
+            // Every marking up to the time limit has been seen
+            RunOutcome::TimeLimit => self
+                .quantifier
+                .time_bound()
+                .is_some_and(|bound| bound <= time),
+            RunOutcome::StepLimit | RunOutcome::Stopped { .. } => false,
+        };
+        complete.then_some(!self.quantifier.is_eventually())
+    }
+}
+
+impl RunCheck for Query {
+    fn check(&self, tapn: &mut Tapn) -> Result<Option<bool>, TapnError> {
+        if let Some(answer) = self.decide(&tapn.marking, tapn.current_time) {
+            return Ok(Some(answer));
+        }
+
+        let outcome = loop {
+            match tapn.step()? {
+                StepOutcome::Fired { time, .. } => {
+                    if let Some(answer) = self.decide(&tapn.marking, time) {
+                        return Ok(Some(answer));
+                    }
+                }
+                StepOutcome::Deadlock => break RunOutcome::Deadlock,
+                StepOutcome::TimeLock => break RunOutcome::TimeLock,
+                StepOutcome::TimeLimit => break RunOutcome::TimeLimit,
+                StepOutcome::StepLimit => break RunOutcome::StepLimit,
+            }
+        };
+
+        Ok(self.conclude(&outcome, tapn.current_time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petri::testing::{places, transfer};
+    use crate::{Distribution, SimulationObserver};
+
+    /// Fires every time unit, putting the token of `P0` back without ever marking `P1`
+    fn live() -> Tapn {
+        let transition = transfer(0, 0, Distribution::Constant(1.0), 0);
+        let marking = Marking::new(vec![vec![0.0], Vec::new()]);
+        Tapn::new(places(2), vec![transition], marking).unwrap()
+    }
+
+    fn query(source: &str, tapn: &Tapn) -> Query {
+        Query::parse(source, &tapn.places).unwrap()
+    }
+
+    #[test]
+    fn limits_leave_queries_undecided() {
+        let tapn = live().with_step_limit(3);
+        for source in ["AG P1 <= 0", "EF P1 >= 1", "PG<=5 P1 <= 0"] {
+            let mut run = tapn.clone();
+            assert_eq!(query(source, &tapn).check(&mut run).unwrap(), None);
+
+            let mut observer = query(source, &tapn).observer(&tapn);
+            observer.on_completion(&RunOutcome::StepLimit);
+            assert_eq!(observer.result, None);
+        }
+    }
+
+    #[test]
+    fn limits_past_the_time_bound_decide_queries() {
+        let tapn = live().with_time_limit(2.0);
+        let mut run = tapn.clone();
+        let answer = query("PG<=2 P1 <= 0", &tapn).check(&mut run).unwrap();
+        assert_eq!(answer, Some(true));
+        let mut run = tapn.clone();
+        let answer = query("PF<=2 P1 >= 1", &tapn).check(&mut run).unwrap();
+        assert_eq!(answer, Some(false));
+
+        let mut run = tapn.clone();
+        let answer = query("PF<=3 P1 >= 1", &tapn).check(&mut run).unwrap();
+        assert_eq!(answer, None);
+        assert_eq!(
+            query("PG<=2 P1 <= 0", &tapn).conclude(&RunOutcome::TimeLimit, 2.0),
+            Some(true)
+        );
+        assert_eq!(
+            query("AG P1 <= 0", &tapn).conclude(&RunOutcome::Deadlock, 2.0),
+            Some(true)
+        );
+    }
+}
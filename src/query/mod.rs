@@ -0,0 +1,5 @@
+pub mod ast;
+pub mod parser;
+
+pub use ast::{Formula, Quantifier, Query, Term};
+pub use parser::QueryError;
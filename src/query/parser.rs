@@ -0,0 +1,442 @@
+use std::fmt;
+
+use super::{Formula, Quantifier, Query, Term};
+use crate::{Comparison, Place};
+
+/// Reason a query could not be parsed. Offsets count characters from the start of the query,
+/// so a column is the offset plus one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryError {
+    /// A character that doesn't start any token
+    UnexpectedCharacter { offset: usize, character: char },
+    /// A token that doesn't fit at this point of the query
+    UnexpectedToken {
+        offset: usize,
+        expected: &'static str,
+        found: String,
+    },
+    /// The query ended while `expected` was still missing
+    UnexpectedEnd { expected: &'static str },
+    /// A number that is out of range, or fractional where a token count is needed
+    InvalidNumber { offset: usize, text: String },
+    /// A name that is not the name of any place in the net
+    UnknownPlace { offset: usize, name: String },
+}
+
+impl QueryError {
+    /// Character offset of the error in the query text, `None` if the query ended too early
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            QueryError::UnexpectedCharacter { offset, .. }
+            | QueryError::UnexpectedToken { offset, .. }
+            | QueryError::InvalidNumber { offset, .. }
+            | QueryError::UnknownPlace { offset, .. } => Some(*offset),
+            QueryError::UnexpectedEnd { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnexpectedCharacter { offset, character } => {
+                write!(f, "unexpected character `{character}` at column {}", offset + 1)
+            }
+            QueryError::UnexpectedToken {
+                offset,
+                expected,
+                found,
+            } => write!(f, "expected {expected} at column {}, found `{found}`", offset + 1),
+            QueryError::UnexpectedEnd { expected } => {
+                write!(f, "expected {expected}, found the end of the query")
+            }
+            QueryError::InvalidNumber { offset, text } => {
+                write!(f, "invalid number `{text}` at column {}", offset + 1)
+            }
+            QueryError::UnknownPlace { offset, name } => {
+                write!(f, "unknown place `{name}` at column {}", offset + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Name(text) | Token::Number(text) => write!(f, "{text}"),
+            Token::Symbol(symbol) => write!(f, "{symbol}"),
+        }
+    }
+}
+
+/// Longest symbols first, so `<=` isn't read as `<` followed by `=`
+const SYMBOLS: [&str; 14] = [
+    "E<>", "A[]", "<=", ">=", "==", "!=", "&&", "||", "<", ">", "=", "!", "(", ")",
+];
+
+/// Splits the query into tokens, each with the character offset it starts at
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = Vec::new();
+    // Bytes index the source, characters are what errors report
+    let mut rest = source.char_indices().enumerate().peekable();
+
+    while let Some(&(column, (offset, character))) = rest.peek() {
+        if character.is_whitespace() {
+            rest.next();
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| source[offset..].starts_with(**s)) {
+            tokens.push((column, Token::Symbol(symbol)));
+            for _ in 0..symbol.len() {
+                rest.next();
+            }
+        } else if character.is_ascii_digit() {
+            let mut end = offset;
+            while let Some(&(_, (i, c))) = rest.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                rest.next();
+            }
+            tokens.push((column, Token::Number(source[offset..end].to_string())));
+        } else if character.is_alphabetic() || character == '_' {
+            let mut end = offset;
+            while let Some(&(_, (i, c))) = rest.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                rest.next();
+            }
+            tokens.push((column, Token::Name(source[offset..end].to_string())));
+        } else {
+            return Err(QueryError::UnexpectedCharacter {
+                offset: column,
+                character,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of a query
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    places: &'a [Place],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<(usize, Token), QueryError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(QueryError::UnexpectedEnd { expected })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn unexpected(&self, expected: &'static str) -> QueryError {
+        match self.tokens.get(self.position) {
+            Some((offset, token)) => QueryError::UnexpectedToken {
+                offset: *offset,
+                expected,
+                found: token.to_string(),
+            },
+            None => QueryError::UnexpectedEnd { expected },
+        }
+    }
+
+    /// Consumes the symbol or keyword if it comes next
+    fn eat(&mut self, text: &str) -> bool {
+        let matches = match self.peek() {
+            Some(Token::Symbol(symbol)) => *symbol == text,
+            Some(Token::Name(name)) => name == text,
+            _ => false,
+        };
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, text: &str, expected: &'static str) -> Result<(), QueryError> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, QueryError> {
+        let quantifier = if self.eat("EF") || self.eat("E<>") {
+            Quantifier::ExistsEventually
+        } else if self.eat("AG") || self.eat("A[]") {
+            Quantifier::AlwaysGlobally
+        } else if self.eat("PF") {
+            Quantifier::ProbablyEventually(self.time_bound()?)
+        } else if self.eat("PG") {
+            Quantifier::ProbablyGlobally(self.time_bound()?)
+        } else {
+            return Err(self.unexpected("`EF`, `AG`, `E<>`, `A[]`, `PF` or `PG`"));
+        };
+
+        let formula = self.or()?;
+        if self.peek().is_some() {
+            return Err(self.unexpected("`and`, `or` or the end of the query"));
+        }
+
+        Ok(Query {
+            quantifier,
+            formula,
+        })
+    }
+
+    fn time_bound(&mut self) -> Result<f64, QueryError> {
+        self.expect("<=", "`<=` and a time bound")?;
+        match self.next("a time bound")? {
+            (offset, Token::Number(text)) => match text.parse::<f64>() {
+                Ok(bound) if bound.is_finite() => Ok(bound),
+                _ => Err(QueryError::InvalidNumber { offset, text }),
+            },
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("a time bound"))
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Formula, QueryError> {
+        let mut formulas = vec![self.and()?];
+        while self.eat("or") || self.eat("||") {
+            formulas.push(self.and()?);
+        }
+        Ok(flatten(formulas, Formula::Or))
+    }
+
+    fn and(&mut self) -> Result<Formula, QueryError> {
+        let mut formulas = vec![self.not()?];
+        while self.eat("and") || self.eat("&&") {
+            formulas.push(self.not()?);
+        }
+        Ok(flatten(formulas, Formula::And))
+    }
+
+    fn not(&mut self) -> Result<Formula, QueryError> {
+        if self.eat("not") || self.eat("!") {
+            Ok(Formula::Not(Box::new(self.not()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Formula, QueryError> {
+        if self.eat("(") {
+            let formula = self.or()?;
+            self.expect(")", "`)`")?;
+            return Ok(formula);
+        }
+        if self.eat("true") {
+            return Ok(Formula::Bool(true));
+        }
+        if self.eat("false") {
+            return Ok(Formula::Bool(false));
+        }
+
+        let left = self.term()?;
+        let comparison = self.comparison()?;
+        let right = self.term()?;
+        Ok(Formula::Compare(left, comparison, right))
+    }
+
+    fn comparison(&mut self) -> Result<Comparison, QueryError> {
+        let comparison = match self.peek() {
+            Some(Token::Symbol("<")) => Comparison::LessThan,
+            Some(Token::Symbol("<=")) => Comparison::LessOrEqual,
+            Some(Token::Symbol("=" | "==")) => Comparison::Equal,
+            Some(Token::Symbol("!=")) => Comparison::NotEqual,
+            Some(Token::Symbol(">=")) => Comparison::GreaterOrEqual,
+            Some(Token::Symbol(">")) => Comparison::GreaterThan,
+            _ => return Err(self.unexpected("a comparison such as `>=`")),
+        };
+        self.position += 1;
+        Ok(comparison)
+    }
+
+    fn term(&mut self) -> Result<Term, QueryError> {
+        const EXPECTED: &str = "a place name or a token count";
+
+        match self.next(EXPECTED)? {
+            (offset, Token::Number(text)) => text
+                .parse()
+                .map(Term::Constant)
+                .map_err(|_| QueryError::InvalidNumber { offset, text }),
+            (offset, Token::Name(name)) => self
+                .places
+                .iter()
+                .position(|place| place.name == name)
+                .map(Term::Place)
+                .ok_or(QueryError::UnknownPlace { offset, name }),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected(EXPECTED))
+            }
+        }
+    }
+}
+
+/// Avoids wrapping a single operand in a connective
+fn flatten(mut formulas: Vec<Formula>, connective: fn(Vec<Formula>) -> Formula) -> Formula {
+    if formulas.len() == 1 {
+        formulas.remove(0)
+    } else {
+        connective(formulas)
+    }
+}
+
+impl Query {
+    /// Parses a query, resolving place names against the places of the net
+    pub fn parse(source: &str, places: &[Place]) -> Result<Self, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            places,
+        };
+        parser.query()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn places() -> Vec<Place> {
+        ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(id, name)| Place::new(id, name.to_string(), Vec::new()))
+            .collect()
+    }
+
+    fn parse(source: &str) -> Result<Query, QueryError> {
+        Query::parse(source, &places())
+    }
+
+    fn at_least(place: usize, count: usize) -> Formula {
+        Formula::Compare(
+            Term::Place(place),
+            Comparison::GreaterOrEqual,
+            Term::Constant(count),
+        )
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_than_or() {
+        let query = parse("EF not a >= 1 and b >= 2 or c >= 3").unwrap();
+        assert_eq!(
+            query.formula,
+            Formula::Or(vec![
+                Formula::And(vec![Formula::Not(Box::new(at_least(0, 1))), at_least(1, 2)]),
+                at_least(2, 3),
+            ])
+        );
+
+        let query = parse("EF a >= 1 && !(b >= 2 || c >= 3)").unwrap();
+        assert_eq!(
+            query.formula,
+            Formula::And(vec![
+                at_least(0, 1),
+                Formula::Not(Box::new(Formula::Or(vec![at_least(1, 2), at_least(2, 3)]))),
+            ])
+        );
+    }
+
+    #[test]
+    fn symbolic_quantifiers_match_their_letter_forms() {
+        assert_eq!(parse("E<>(a>=1)"), parse("EF (a >= 1)"));
+        assert_eq!(parse("A[] a<=b"), parse("AG a <= b"));
+        assert_eq!(
+            parse("A[] true").unwrap().quantifier,
+            Quantifier::AlwaysGlobally
+        );
+        assert_eq!(
+            tokenize("E<>a<=1").unwrap(),
+            vec![
+                (0, Token::Symbol("E<>")),
+                (3, Token::Name("a".to_string())),
+                (4, Token::Symbol("<=")),
+                (6, Token::Number("1".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn probabilistic_quantifiers_take_a_time_bound() {
+        let query = parse("PF<=10.5 (c >= 1)").unwrap();
+        assert_eq!(query.quantifier, Quantifier::ProbablyEventually(10.5));
+        assert_eq!(query.formula, at_least(2, 1));
+
+        let query = parse("PG <= 3 a = 0").unwrap();
+        assert_eq!(query.quantifier, Quantifier::ProbablyGlobally(3.0));
+
+        assert_eq!(
+            parse("PF (a >= 1)"),
+            Err(QueryError::UnexpectedToken {
+                offset: 3,
+                expected: "`<=` and a time bound",
+                found: "(".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("PF<=1.2.3 true"),
+            Err(QueryError::InvalidNumber {
+                offset: 4,
+                text: "1.2.3".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_character() {
+        let offset = |source| parse(source).unwrap_err().offset();
+        assert_eq!(offset("EF a >= 1 $"), Some(10));
+        assert_eq!(offset("EF é >= 1"), Some(3));
+        assert_eq!(offset("EF é.b >= 1 $"), Some(12));
+        assert_eq!(
+            parse("EF é.b >= 1 $").unwrap_err().to_string(),
+            "unexpected character `$` at column 13"
+        );
+        assert_eq!(offset("EF (a >= 1"), None);
+        assert_eq!(offset("EF a >= 1 b"), Some(10));
+        assert_eq!(offset("XF a >= 1"), Some(0));
+
+        assert_eq!(
+            parse("EF (d >= 1)"),
+            Err(QueryError::UnknownPlace {
+                offset: 4,
+                name: "d".to_string(),
+            })
+        );
+        assert_eq!(
+            parse("EF a >= 1.5"),
+            Err(QueryError::InvalidNumber {
+                offset: 8,
+                text: "1.5".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::ops::Range;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::{RunResult, Tapn, TapnError, derive_seed};
+
+/// Runs many independent simulations of a model spread over several threads.
+/// Run `i` is seeded with `derive_seed(base_seed, i)`, so the results only depend on
+/// the base seed and the number of runs, never on the number of threads.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    pub runs: usize,
+    pub base_seed: u64,
+    /// Number of worker threads, defaults to the available parallelism
+    pub threads: usize,
+}
+
+/// Aggregated results of a batch, the individual runs are kept in run order
+#[derive(Clone, Debug)]
+pub struct BatchResult {
+    pub base_seed: u64,
+    pub runs: Vec<RunResult>,
+}
+
+impl Batch {
+    pub fn new(runs: usize, base_seed: u64) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            runs,
+            base_seed,
+            threads,
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Seed of the run with the given index
+    pub fn seed(&self, run: usize) -> u64 {
+        derive_seed(self.base_seed, run as u64)
+    }
+
+    /// Runs clones of `model`, each from its own seed
+    pub fn run(&self, model: &Tapn) -> Result<BatchResult, TapnError> {
+        self.run_with(|_| model.clone())
+    }
+
+    /// Builds the model of every run with `factory`, which gets the run index.
+    /// The model is reset and reseeded with the run's seed before it starts.
+    /// Fails with the error of the first run, in run order, that could not be simulated.
+    pub fn run_with<F>(&self, factory: F) -> Result<BatchResult, TapnError>
+    where
+        F: Fn(usize) -> Tapn + Sync,
+    {
+        let runs = self.map(0..self.runs, |run, seed| {
+            let mut tapn = factory(run);
+            tapn.reset();
+            tapn.reseed(seed);
+            tapn.run()
+        });
+
+        Ok(BatchResult {
+            base_seed: self.base_seed,
+            runs: runs.into_iter().collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Calls `f` with the index and seed of every run in `runs` on the worker threads
+    /// and returns the results in run order
+    pub fn map<T, F>(&self, runs: Range<usize>, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, u64) -> T + Sync,
+    {
+        let next = AtomicUsize::new(runs.start);
+        let results = Mutex::new(Vec::with_capacity(runs.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(runs.len()) {
+                scope.spawn(|| {
+                    let mut finished = Vec::new();
+                    // Workers take the next run until none are left
+                    loop {
+                        let run = next.fetch_add(1, Ordering::Relaxed);
+                        if run >= runs.end {
+                            break;
+                        }
+                        finished.push((run, f(run, self.seed(run))));
+                    }
+                    results.lock().unwrap().extend(finished);
+                });
+            }
+        });
+
+        // Restore run order so aggregates are summed the same way for any thread count
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|&(run, _)| run);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+impl BatchResult {
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    pub fn total_steps(&self) -> usize {
+        self.runs.iter().map(|run| run.steps).sum()
+    }
+
+    pub fn mean_steps(&self) -> f64 {
+        self.total_steps() as f64 / self.len() as f64
+    }
+
+    pub fn mean_end_time(&self) -> f64 {
+        self.runs.iter().map(|run| run.end_time).sum::<f64>() / self.len() as f64
+    }
+
+    /// Number of times the transition fired over all runs
+    pub fn total_firings(&self, transition: usize) -> usize {
+        self.runs
+            .iter()
+            .map(|run| run.firings.get(transition).copied().unwrap_or(0))
+            .sum()
+    }
+
+    pub fn mean_firings(&self, transition: usize) -> f64 {
+        self.total_firings(transition) as f64 / self.len() as f64
+    }
+}
@@ -0,0 +1,9 @@
+pub mod batch;
+pub mod property;
+pub mod smc;
+pub mod sprt;
+
+pub use batch::{Batch, BatchResult};
+pub use property::{PlaceCondition, Property, RunCheck};
+pub use smc::{Bound, Estimate, Estimator, ParameterError, clopper_pearson};
+pub use sprt::{Decision, HypothesisTest, Verdict};
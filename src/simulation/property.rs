@@ -0,0 +1,84 @@
+use crate::{Comparison, Marking, StepOutcome, Tapn, TapnError};
+
+/// Number of tokens a place must have, compared against a threshold
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlaceCondition {
+    /// Index of the place in the net
+    pub place: usize,
+    pub threshold: usize,
+    pub comparison: Comparison,
+}
+
+/// Time-bounded reachability: is a marking in which every condition holds reached
+/// no later than `time_bound`?
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub conditions: Vec<PlaceCondition>,
+    pub time_bound: f64,
+}
+
+impl PlaceCondition {
+    pub fn holds(&self, marking: &Marking) -> bool {
+        self.comparison
+            .holds(marking.count(self.place), self.threshold)
+    }
+}
+
+impl Property {
+    pub fn within(time_bound: f64) -> Self {
+        Self {
+            conditions: Vec::new(),
+            time_bound,
+        }
+    }
+
+    pub fn with_condition(mut self, place: usize, threshold: usize, comparison: Comparison) -> Self {
+        self.conditions.push(PlaceCondition {
+            place,
+            threshold,
+            comparison,
+        });
+        self
+    }
+
+    /// Checks if every condition holds in the marking
+    pub fn holds(&self, marking: &Marking) -> bool {
+        self.conditions.iter().all(|condition| condition.holds(marking))
+    }
+}
+
+/// A yes or no question about a single simulated run of a net
+pub trait RunCheck: Sync {
+    /// Simulates the net from its current state until the answer for this run is known,
+    /// `None` if a time or step limit ends the run first
+    fn check(&self, tapn: &mut Tapn) -> Result<Option<bool>, TapnError>;
+
+    /// Answers the question for a run of a clone of `model` from its initial marking,
+    /// with every random stream derived from `seed`
+    fn sample(&self, model: &Tapn, seed: u64) -> Result<Option<bool>, TapnError> {
+        let mut tapn = model.clone();
+        tapn.reset();
+        tapn.reseed(seed);
+        self.check(&mut tapn)
+    }
+}
+
+impl RunCheck for Property {
+    /// The marking only changes when a transition fires, so it's checked at the start and
+    /// after every firing up to the time bound. Runs that end earlier falsify the property,
+    /// unless a limit cut them off before the time bound.
+    fn check(&self, tapn: &mut Tapn) -> Result<Option<bool>, TapnError> {
+        loop {
+            if self.holds(&tapn.marking) {
+                return Ok(Some(true));
+            }
+
+            match tapn.step()? {
+                StepOutcome::Fired { time, .. } if time <= self.time_bound => {}
+                StepOutcome::TimeLimit if tapn.current_time < self.time_bound => return Ok(None),
+                StepOutcome::StepLimit => return Ok(None),
+                _ => return Ok(Some(false)),
+            }
+        }
+    }
+}
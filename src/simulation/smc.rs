@@ -0,0 +1,389 @@
+use std::fmt;
+
+use rand::Rng;
+
+use super::{Batch, RunCheck};
+use crate::{Tapn, TapnError};
+
+/// Runs simulated in one go by the sequential tests before they look at the samples again.
+/// Fixed so the result doesn't depend on the number of threads.
+pub(super) const CHUNK: usize = 256;
+
+/// How the number of runs and the confidence interval of an estimate are determined
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bound {
+    /// Runs the number of simulations the Chernoff-Hoeffding bound requires up front,
+    /// the interval is the estimate plus or minus the error
+    #[default]
+    ChernoffHoeffding,
+    /// Keeps simulating until the exact Clopper-Pearson interval is no wider than twice
+    /// the error, never running more simulations than the Chernoff-Hoeffding bound
+    ClopperPearson,
+}
+
+/// A parameter of a statistical test outside the range it must lie in
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterError {
+    /// The named probability doesn't lie strictly between 0 and 1
+    OutOfRange { name: &'static str, value: f64 },
+    /// The indifference region around a threshold doesn't lie strictly between 0 and 1
+    IndifferenceRegion { threshold: f64, indifference: f64 },
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterError::OutOfRange { name, value } => {
+                write!(f, "{name} must lie strictly between 0 and 1, got {value}")
+            }
+            ParameterError::IndifferenceRegion {
+                threshold,
+                indifference,
+            } => write!(
+                f,
+                "indifference region {threshold} ± {indifference} must lie strictly between 0 and 1"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// Checks that the probability `value` lies strictly between 0 and 1
+pub(super) fn probability(name: &'static str, value: f64) -> Result<f64, ParameterError> {
+    if value > 0.0 && value < 1.0 {
+        Ok(value)
+    } else {
+        Err(ParameterError::OutOfRange { name, value })
+    }
+}
+
+/// Statistical model checker estimating the probability that a run satisfies a property
+#[derive(Clone, Debug)]
+pub struct Estimator {
+    /// Probability that the true value lies in the reported interval, such as `0.95`
+    pub confidence: f64,
+    /// Largest accepted distance between the estimate and the true probability
+    pub error: f64,
+    pub bound: Bound,
+    pub base_seed: u64,
+    pub threads: Option<usize>,
+}
+
+/// Probability estimated by an `Estimator`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// Fraction of runs that satisfied the property, counting undecided runs as unsatisfied
+    pub probability: f64,
+    /// Lower and upper end of the confidence interval, which holds whichever way the
+    /// undecided runs would have gone
+    pub interval: (f64, f64),
+    pub confidence: f64,
+    pub runs: usize,
+    /// Runs that satisfied the property
+    pub successes: usize,
+    /// Runs a time or step limit ended before the property was answered
+    pub inconclusive: usize,
+    /// Seed that reproduces this estimate when passed to `Estimator::with_seed`
+    pub base_seed: u64,
+}
+
+impl Estimator {
+    /// Fails if `confidence` or `error` doesn't lie strictly between 0 and 1
+    pub fn new(confidence: f64, error: f64) -> Result<Self, ParameterError> {
+        Ok(Self {
+            confidence: probability("confidence", confidence)?,
+            error: probability("error", error)?,
+            bound: Bound::default(),
+            base_seed: rand::rng().random(),
+            threads: None,
+        })
+    }
+
+    pub fn with_bound(mut self, bound: Bound) -> Self {
+        self.bound = bound;
+        self
+    }
+
+    pub fn with_seed(mut self, base_seed: u64) -> Self {
+        self.base_seed = base_seed;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Number of runs after which the Chernoff-Hoeffding bound guarantees the error
+    pub fn max_runs(&self) -> usize {
+        let alpha = 1.0 - self.confidence;
+        ((2.0 / alpha).ln() / (2.0 * self.error * self.error)).ceil() as usize
+    }
+
+    /// Estimates the probability that a run of `model` from its initial marking satisfies
+    /// `property`. Every run uses a clone of the model with its own seed.
+    pub fn estimate<P: RunCheck + ?Sized>(
+        &self,
+        model: &Tapn,
+        property: &P,
+    ) -> Result<Estimate, TapnError> {
+        let max_runs = self.max_runs();
+        let batch = batch(max_runs, self.base_seed, self.threads);
+
+        let mut runs = 0;
+        let mut successes = 0;
+        let mut inconclusive = 0;
+        let mut interval = (0.0, 1.0);
+
+        while runs < max_runs {
+            let end = match self.bound {
+                Bound::ChernoffHoeffding => max_runs,
+                Bound::ClopperPearson => (runs + CHUNK).min(max_runs),
+            };
+            for answer in batch.map(runs..end, |_, seed| property.sample(model, seed)) {
+                match answer? {
+                    Some(true) => successes += 1,
+                    Some(false) => {}
+                    None => inconclusive += 1,
+                }
+            }
+            runs = end;
+
+            // The lower end counts undecided runs as unsatisfied, the upper one as satisfied
+            if self.bound == Bound::ClopperPearson {
+                let alpha = 1.0 - self.confidence;
+                interval = (
+                    clopper_pearson(successes, runs, alpha).0,
+                    clopper_pearson(successes + inconclusive, runs, alpha).1,
+                );
+                if interval.1 - interval.0 <= 2.0 * self.error {
+                    break;
+                }
+            }
+        }
+
+        let probability = successes as f64 / runs as f64;
+        if self.bound == Bound::ChernoffHoeffding {
+            let undecided = inconclusive as f64 / runs as f64;
+            interval = (
+                (probability - self.error).max(0.0),
+                (probability + undecided + self.error).min(1.0),
+            );
+        }
+
+        Ok(Estimate {
+            probability,
+            interval,
+            confidence: self.confidence,
+            runs,
+            successes,
+            inconclusive,
+            base_seed: self.base_seed,
+        })
+    }
+}
+
+/// Batch with the given thread count, or one thread per core if there is none
+pub(super) fn batch(runs: usize, base_seed: u64, threads: Option<usize>) -> Batch {
+    let batch = Batch::new(runs, base_seed);
+    match threads {
+        Some(threads) => batch.with_threads(threads),
+        None => batch,
+    }
+}
+
+/// Exact binomial confidence interval for `successes` out of `runs` at level `1 - alpha`
+pub fn clopper_pearson(successes: usize, runs: usize, alpha: f64) -> (f64, f64) {
+    let x = successes as f64;
+    let n = runs as f64;
+
+    let lower = if successes == 0 {
+        0.0
+    } else {
+        beta_quantile(alpha / 2.0, x, n - x + 1.0)
+    };
+    let upper = if successes == runs {
+        1.0
+    } else {
+        beta_quantile(1.0 - alpha / 2.0, x + 1.0, n - x)
+    };
+
+    (lower, upper)
+}
+
+/// Inverse of the regularized incomplete beta function, found by bisection
+fn beta_quantile(p: f64, a: f64, b: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if incomplete_beta(mid, a, b) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln()
+        + b * (1.0 - x).ln())
+    .exp();
+
+    // The continued fraction converges quickly on this side of the mean, use symmetry otherwise
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Continued fraction of the incomplete beta function, evaluated with Lentz's method
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    const EPSILON: f64 = 1e-15;
+
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut fraction = d;
+
+    for m in 1..1000 {
+        let m = m as f64;
+
+        // Even step
+        let numerator = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + numerator * d);
+        c = clamp(1.0 + numerator / c);
+        fraction *= d * c;
+
+        // Odd step
+        let numerator = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + numerator * d);
+        c = clamp(1.0 + numerator / c);
+        let delta = d * c;
+        fraction *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    fraction
+}
+
+/// Natural logarithm of the gamma function using the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |sum, (j, c)| sum + c / (x + 1.0 + j as f64));
+
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petri::testing::uniform_transfer;
+    use crate::{Comparison, Property};
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 5e-5
+    }
+
+    #[test]
+    fn clopper_pearson_matches_known_intervals() {
+        let (lower, upper) = clopper_pearson(5, 10, 0.05);
+        assert!(close(lower, 0.1871), "{lower}");
+        assert!(close(upper, 0.8129), "{upper}");
+
+        let (lower, upper) = clopper_pearson(0, 738, 0.05);
+        assert_eq!(lower, 0.0);
+        assert!(close(upper, 0.004986), "{upper}");
+
+        let (lower, upper) = clopper_pearson(20, 20, 0.05);
+        assert!(close(lower, 0.8316), "{lower}");
+        assert_eq!(upper, 1.0);
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        for (n, factorial) in [(1.0, 1.0), (5.0, 24.0), (11.0, 3_628_800.0_f64)] {
+            assert!((ln_gamma(n) - factorial.ln()).abs() < 1e-9);
+        }
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parameters_outside_the_unit_interval_are_rejected() {
+        assert!(Estimator::new(0.95, 0.01).is_ok());
+        assert_eq!(
+            Estimator::new(1.5, 0.01).unwrap_err(),
+            ParameterError::OutOfRange {
+                name: "confidence",
+                value: 1.5
+            }
+        );
+        assert!(Estimator::new(1.0, 0.01).is_err());
+        assert!(Estimator::new(0.95, 0.0).is_err());
+        assert!(Estimator::new(0.95, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn same_seed_gives_same_estimate() {
+        let model = uniform_transfer();
+        let property = Property::within(0.5).with_condition(1, 1, Comparison::GreaterOrEqual);
+        for bound in [Bound::ChernoffHoeffding, Bound::ClopperPearson] {
+            let estimator = Estimator::new(0.9, 0.05)
+                .unwrap()
+                .with_bound(bound)
+                .with_seed(3);
+            let first = estimator.estimate(&model, &property).unwrap();
+            let single = estimator.with_threads(1);
+            let second = single.estimate(&model, &property).unwrap();
+            assert_eq!(first, second);
+            assert!((first.probability - 0.5).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn undecided_runs_widen_the_interval() {
+        // The limit ends the three quarters of the runs in which the token is still on its way
+        let model = uniform_transfer().with_time_limit(0.25);
+        let property = Property::within(0.5).with_condition(1, 1, Comparison::GreaterOrEqual);
+        for bound in [Bound::ChernoffHoeffding, Bound::ClopperPearson] {
+            let estimator = Estimator::new(0.9, 0.05)
+                .unwrap()
+                .with_bound(bound)
+                .with_seed(3);
+            let estimate = estimator.estimate(&model, &property).unwrap();
+            assert_eq!(estimate.successes + estimate.inconclusive, estimate.runs);
+            assert!((estimate.probability - 0.25).abs() < 0.05);
+            assert!(estimate.interval.0 < 0.25);
+            assert_eq!(estimate.interval.1, 1.0);
+        }
+    }
+}
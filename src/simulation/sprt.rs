@@ -0,0 +1,218 @@
+use rand::Rng;
+
+use super::RunCheck;
+use super::smc::{CHUNK, ParameterError, batch, probability};
+use crate::{Tapn, TapnError};
+
+/// Wald's sequential probability ratio test deciding whether the probability that a run
+/// satisfies a property is at least `threshold`.
+///
+/// The test weighs `p >= threshold + indifference` against `p <= threshold - indifference`,
+/// so probabilities closer to the threshold than `indifference` may go either way.
+#[derive(Clone, Debug)]
+pub struct HypothesisTest {
+    pub threshold: f64,
+    /// Half-width of the indifference region around the threshold
+    pub indifference: f64,
+    /// Probability of deciding `Below` although `p >= threshold + indifference`
+    pub alpha: f64,
+    /// Probability of deciding `AtLeast` although `p <= threshold - indifference`
+    pub beta: f64,
+    pub base_seed: u64,
+    pub threads: Option<usize>,
+}
+
+/// Answer of a hypothesis test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// The probability is at least the threshold
+    AtLeast,
+    /// The probability is below the threshold
+    Below,
+    /// Whether the probability is at least the threshold depends on the runs a time or step
+    /// limit ended before the property was answered
+    Inconclusive,
+}
+
+/// Decision of a `HypothesisTest` and the samples it took to reach it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub decision: Decision,
+    pub runs: usize,
+    /// Runs that satisfied the property
+    pub successes: usize,
+    /// Runs a time or step limit ended before the property was answered
+    pub inconclusive: usize,
+    /// Seed that reproduces this verdict when passed to `HypothesisTest::with_seed`
+    pub base_seed: u64,
+}
+
+impl HypothesisTest {
+    /// Fails if `alpha`, `beta` or the indifference region around `threshold` doesn't lie
+    /// strictly between 0 and 1
+    pub fn new(
+        threshold: f64,
+        indifference: f64,
+        alpha: f64,
+        beta: f64,
+    ) -> Result<Self, ParameterError> {
+        if !(indifference > 0.0 && threshold - indifference > 0.0 && threshold + indifference < 1.0)
+        {
+            return Err(ParameterError::IndifferenceRegion {
+                threshold,
+                indifference,
+            });
+        }
+
+        Ok(Self {
+            threshold,
+            indifference,
+            alpha: probability("alpha", alpha)?,
+            beta: probability("beta", beta)?,
+            base_seed: rand::rng().random(),
+            threads: None,
+        })
+    }
+
+    pub fn with_seed(mut self, base_seed: u64) -> Self {
+        self.base_seed = base_seed;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Simulates runs of `model` from its initial marking until the test can decide
+    /// whether `property` holds with at least the threshold probability.
+    /// Runs are simulated in chunks, samples after the deciding one are ignored.
+    pub fn decide<P: RunCheck + ?Sized>(
+        &self,
+        model: &Tapn,
+        property: &P,
+    ) -> Result<Verdict, TapnError> {
+        let p0 = self.threshold + self.indifference;
+        let p1 = self.threshold - self.indifference;
+
+        // Log-likelihood ratio of the alternative `p <= p1` against `p >= p0` per sample
+        let success = (p1 / p0).ln();
+        let failure = ((1.0 - p1) / (1.0 - p0)).ln();
+        let accept_below = ((1.0 - self.beta) / self.alpha).ln();
+        let accept_at_least = (self.beta / (1.0 - self.alpha)).ln();
+
+        // Adds a sample to a test that hasn't decided yet
+        let observe = |test: &mut Wald, satisfied: bool| {
+            if test.decision.is_none() {
+                test.ratio += if satisfied { success } else { failure };
+                if test.ratio >= accept_below {
+                    test.decision = Some(Decision::Below);
+                } else if test.ratio <= accept_at_least {
+                    test.decision = Some(Decision::AtLeast);
+                }
+            }
+        };
+
+        // Undecided runs count as failures in one test and as successes in the other,
+        // both agree unless the answer depends on them
+        let mut pessimistic = Wald::default();
+        let mut optimistic = Wald::default();
+
+        let batch = batch(usize::MAX, self.base_seed, self.threads);
+        let mut runs = 0;
+        let mut successes = 0;
+        let mut inconclusive = 0;
+
+        loop {
+            for answer in batch.map(runs..runs + CHUNK, |_, seed| property.sample(model, seed)) {
+                let answer = answer?;
+                runs += 1;
+                match answer {
+                    Some(true) => successes += 1,
+                    Some(false) => {}
+                    None => inconclusive += 1,
+                }
+                observe(&mut pessimistic, answer == Some(true));
+                observe(&mut optimistic, answer != Some(false));
+
+                let (Some(low), Some(high)) = (pessimistic.decision, optimistic.decision) else {
+                    continue;
+                };
+                let decision = if low == high {
+                    low
+                } else {
+                    Decision::Inconclusive
+                };
+
+                return Ok(Verdict {
+                    decision,
+                    runs,
+                    successes,
+                    inconclusive,
+                    base_seed: self.base_seed,
+                });
+            }
+        }
+    }
+}
+
+/// One of Wald's tests, run until its log-likelihood ratio first crosses a bound
+#[derive(Default)]
+struct Wald {
+    ratio: f64,
+    decision: Option<Decision>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::petri::testing::uniform_transfer;
+    use crate::{Comparison, Property};
+
+    #[test]
+    fn parameters_outside_the_unit_interval_are_rejected() {
+        assert!(HypothesisTest::new(0.5, 0.01, 0.05, 0.05).is_ok());
+        assert_eq!(
+            HypothesisTest::new(0.005, 0.01, 0.05, 0.05).unwrap_err(),
+            ParameterError::IndifferenceRegion {
+                threshold: 0.005,
+                indifference: 0.01
+            }
+        );
+        assert!(HypothesisTest::new(0.995, 0.01, 0.05, 0.05).is_err());
+        assert!(HypothesisTest::new(0.5, 0.0, 0.05, 0.05).is_err());
+        assert!(HypothesisTest::new(0.5, 0.01, 0.0, 0.05).is_err());
+        assert!(HypothesisTest::new(0.5, 0.01, 0.05, 1.0).is_err());
+    }
+
+    #[test]
+    fn decides_on_the_right_side_of_the_threshold() {
+        // The token reaches place 1 by time 0.5 in half of the runs
+        let model = uniform_transfer();
+        let property = Property::within(0.5).with_condition(1, 1, Comparison::GreaterOrEqual);
+        for (threshold, expected) in [(0.3, Decision::AtLeast), (0.7, Decision::Below)] {
+            let test = HypothesisTest::new(threshold, 0.05, 0.01, 0.01)
+                .unwrap()
+                .with_seed(5);
+            let verdict = test.decide(&model, &property).unwrap();
+            assert_eq!(verdict.decision, expected);
+            assert_eq!(test.decide(&model, &property).unwrap(), verdict);
+        }
+    }
+
+    #[test]
+    fn undecided_runs_only_decide_when_they_cannot_change_the_answer() {
+        // Between a quarter and all of the runs satisfy the property, depending on the
+        // three quarters that the limit ends while the token is still on its way
+        let model = uniform_transfer().with_time_limit(0.25);
+        let property = Property::within(0.5).with_condition(1, 1, Comparison::GreaterOrEqual);
+        for (threshold, expected) in [(0.1, Decision::AtLeast), (0.5, Decision::Inconclusive)] {
+            let test = HypothesisTest::new(threshold, 0.05, 0.01, 0.01)
+                .unwrap()
+                .with_seed(5);
+            let verdict = test.decide(&model, &property).unwrap();
+            assert_eq!(verdict.decision, expected);
+            assert!(verdict.inconclusive > 0);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+/// Puts the token of `a` back every time unit and never marks `b`, so runs only end at a limit
+const LIVE: &str = r#"<pnml>
+  <net active="true" id="live">
+    <place id="a" name="a" initialMarking="1"/>
+    <place id="b" name="b" initialMarking="0"/>
+    <transition id="t" name="t" distribution="constant" value="1"/>
+    <arc id="in" inscription="[0,inf)" source="a" target="t" type="timed" weight="1"/>
+    <arc id="out" inscription="1" source="t" target="a" type="normal" weight="1"/>
+  </net>
+</pnml>"#;
+
+/// Model file only one test uses, removed when dropped
+struct Model(PathBuf);
+
+impl Model {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("tapn-{}-{name}.tapn", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        Model(path)
+    }
+
+    /// Runs `command` on the model with the given options
+    fn run(&self, command: &str, options: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_tapn"))
+            .arg(command)
+            .arg(&self.0)
+            .args(options)
+            .output()
+            .unwrap()
+    }
+
+    /// Runs a command that should succeed and parses its JSON output
+    fn json(&self, command: &str, options: &[&str]) -> Value {
+        let options = [options, &["--format", "json", "--seed", "1"]].concat();
+        let output = self.run(command, &options);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+}
+
+impl Drop for Model {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn check_rejects_unbounded_queries_without_a_limit() {
+    let model = Model::new("unbounded", LIVE);
+    let output = model.run("check", &["-q", "EF b >= 1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--time-limit"));
+}
+
+#[test]
+fn check_counts_runs_cut_off_by_a_limit_as_inconclusive() {
+    let model = Model::new("limited", LIVE);
+
+    let estimate = model.json(
+        "check",
+        &["-q", "EF b >= 1", "--time-limit", "5", "--error", "0.1"],
+    );
+    assert_eq!(estimate["successes"], 0);
+    assert_eq!(estimate["inconclusive"], estimate["runs"]);
+    assert_eq!(estimate["lower"], 0.0);
+    assert_eq!(estimate["upper"], 1.0);
+
+    let verdict = model.json(
+        "check",
+        &["-q", "AG b <= 0", "--step-limit=20", "--threshold=0.5"],
+    );
+    assert_eq!(verdict["decision"], "inconclusive");
+
+    // A limit past the time bound leaves every run decided
+    let estimate = model.json(
+        "check",
+        &["-q", "PG<=3 b <= 0", "--time-limit", "5", "--error", "0.1"],
+    );
+    assert_eq!(estimate["probability"], 1.0);
+    assert_eq!(estimate["inconclusive"], 0);
+}
+
+#[test]
+fn simulate_needs_runs_that_end() {
+    let model = Model::new("simulate", LIVE);
+    for options in [&[][..], &["-q", "EF b >= 1"]] {
+        let output = model.run("simulate", &[options, &["-n", "2"]].concat());
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--step-limit"));
+    }
+
+    let summary = model.json("simulate", &["-n", "2", "--step-limit", "3"]);
+    assert_eq!(summary["outcomes"]["step_limit"], 2);
+
+    let summary = model.json("simulate", &["-n", "2", "-q", "PG<=2 b <= 0"]);
+    assert_eq!(summary["satisfied"], 1.0);
+}